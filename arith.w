@@ -0,0 +1,33 @@
+fn constants()
+{
+    println("{} == 5", 10 - 3 - 2);
+    println("{} == 1", 8 / 4 / 2);
+    println("{} == 1", 17 % 7 % 2);
+    println("{} == 14", 2 + 3 * 4);
+    println("{} == 20", (2 + 3) * 4);
+    println("{} == -7", -3 - 4);
+    println("{} == 6", -2 * -3);
+    println("{} == 3", +3);
+    println("{} == 16", 1 << 2 << 2);
+    println("{} == 9", 1 + 1 << 2 | 1);
+    println("{} == true", 1 + 2 == 3 && 2 * 2 > 3);
+}
+
+fn variables()
+{
+    i64 a = 10;
+    i64 b = 3;
+    i64 c = 2;
+    println("{} == 5", a - b - c);
+    println("{} == 1", a / b / c);
+    println("{} == -16", -a - b * c);
+    println("{} == 0", a - (b + c) * 2);
+    f64 d = 1.5;
+    println("{:.2} == 0.25", d - 1 - 0.25);
+}
+
+fn main(i64 argc) -> i64
+{
+    constants();
+    variables();
+}
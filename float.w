@@ -1,3 +1,3 @@
-f64 a = 3.14
-f64 b = a
-print(a)
\ No newline at end of file
+f64 a = 3.14;
+f64 b = a;
+print(a)
@@ -0,0 +1,129 @@
+# First-fit free list over chunks of memory mapped from the kernel.
+# Every block starts with a 16 byte header: the size of the block without it,
+# and, while the block is free, the next free block.
+.set    ALLOC_CHUNK, 0x100000      # Smallest amount of memory mapped at once
+.set    ALLOC_MAX, 0x800000000000  # Sizes above it can't be mapped, negative ones included
+
+.text
+.globl alloc
+alloc:
+  pushq   %rbx                     # Save rbx, we keep the size in it
+  movabsq $ALLOC_MAX, %rax         # Largest size handed out
+  cmpq    %rax, %rdi               # Compare it to the size, unsigned
+  ja      .alloc_fail              # If it's bigger, rounding it up would wrap, return null
+  addq    $15, %rdi                # Round the size up to a multiple of 16,
+  andq    $-16, %rdi               # so every block stays 16 byte aligned
+  jnz     .alloc_sized             # If the size is zero,
+  movq    $16, %rdi                # still hand out a unique block
+  .alloc_sized:
+  movq    %rdi, %rbx               # Save size
+  leaq    .free_list(%rip), %rcx   # Link pointing to the current free block
+  .alloc_find:
+  movq    (%rcx), %rax             # Current free block
+  testq   %rax, %rax               # Test if we're at the end of the free list
+  jz      .alloc_bump              # If so, take a new block from the chunk
+  cmpq    %rbx, (%rax)             # Compare the size of the block to the one needed
+  jae     .alloc_found             # If it's big enough, take it
+  leaq    8(%rax), %rcx            # Link to the next free block
+  jmp     .alloc_find              # Keep looking
+  .alloc_found:
+  movq    8(%rax), %rdx            # Next free block
+  movq    %rdx, (%rcx)             # Unlink the block
+  addq    $16, %rax                # Skip the header
+  popq    %rbx                     # Restore rbx
+  ret                              # Return the block
+  .alloc_bump:
+  movq    .heap_top(%rip), %rax    # Start of the unused part of the chunk
+  leaq    16(%rax, %rbx), %rdx     # End of the new block
+  cmpq    .heap_end(%rip), %rdx    # Test if it fits into the chunk
+  jbe     .alloc_take              # If so, take it
+  leaq    4111(%rbx), %rdx         # Size of the block with its header,
+  andq    $-4096, %rdx             # rounded up to whole pages
+  movq    $ALLOC_CHUNK, %rax       # Map at least a whole chunk
+  cmpq    %rax, %rdx               # Compare the size to the chunk
+  cmovbq  %rax, %rdx               # Take the bigger one
+  pushq   %rdx                     # Save the length of the mapping
+  pushq   $0                       # Offset
+  movq    $-1, %r9                 # No file descriptor
+  movq    $0x22, %r8               # MAP_PRIVATE | MAP_ANONYMOUS
+  movq    $3, %rcx                 # PROT_READ | PROT_WRITE
+  xorl    %esi, %esi               # Let the kernel choose the address
+  movq    $9, %rdi                 # Syscall number for mmap
+  call    syscall6                 # Perform syscall, the length is in rdx already
+  addq    $8, %rsp                 # Drop the offset
+  popq    %rdx                     # Restore the length of the mapping
+  cmpq    $-4096, %rax             # Errors are returned as -errno
+  ja      .alloc_fail              # If it failed, return null
+  movq    %rax, .heap_top(%rip)    # The rest of the previous chunk is left unused,
+  addq    %rax, %rdx               # the new one starts right at the mapping
+  movq    %rdx, .heap_end(%rip)    # End of the new chunk
+  .alloc_take:
+  movq    %rbx, (%rax)             # Store the size in the header
+  leaq    16(%rax, %rbx), %rdx     # End of the block
+  movq    %rdx, .heap_top(%rip)    # Bump the top of the chunk
+  addq    $16, %rax                # Skip the header
+  popq    %rbx                     # Restore rbx
+  ret                              # Return the block
+  .alloc_fail:
+  xorl    %eax, %eax               # Return null
+  popq    %rbx                     # Restore rbx
+  ret                              # Return from function
+
+.globl free
+free:
+  testq   %rdi, %rdi               # Test if the pointer is null
+  jz      .free_done               # If so, there's nothing to free
+  subq    $16, %rdi                # Header of the block
+  movq    .free_list(%rip), %rax   # Current head of the free list
+  movq    %rax, 8(%rdi)            # Link the block in front of it
+  movq    %rdi, .free_list(%rip)   # The block is the new head
+  .free_done:
+  ret                              # Return from function
+
+.globl realloc
+realloc:
+  movabsq $ALLOC_MAX, %rax         # Largest size handed out
+  cmpq    %rax, %rsi               # Compare it to the size, unsigned
+  ja      .realloc_fail            # If it's bigger, keep the block and return null
+  testq   %rdi, %rdi               # Test if the pointer is null
+  jnz     .realloc_block           # If not, resize the block
+  movq    %rsi, %rdi               # Otherwise it's just an allocation
+  jmp     alloc                    # Tail call
+  .realloc_block:
+  cmpq    %rsi, -16(%rdi)          # Compare the size of the block to the one needed
+  jb      .realloc_move            # If it's too small, move it
+  movq    %rdi, %rax               # Otherwise keep the block
+  ret                              # Return it
+  .realloc_fail:
+  xorl    %eax, %eax               # Return null
+  ret                              # Return from function
+  .realloc_move:
+  pushq   %rbx                     # Save rbx, we keep the old block in it
+  pushq   %r12                     # Save r12, we keep the new block in it
+  subq    $8, %rsp                 # Keep the stack aligned
+  movq    %rdi, %rbx               # Save old block
+  movq    %rsi, %rdi               # Size of the new block
+  call    alloc                    # Allocate it
+  testq   %rax, %rax               # Test if the allocation failed
+  jz      .realloc_done            # If so, the old block is kept, and null returned
+  movq    %rax, %r12               # Save new block
+  movq    -16(%rbx), %rcx          # Size of the old block
+  movq    %rbx, %rsi               # Copy from the old block
+  movq    %rax, %rdi               # Into the new one
+  rep movsb                        # Copy
+  movq    %rbx, %rdi               # Old block
+  call    free                     # Free it
+  movq    %r12, %rax               # Return the new block
+  .realloc_done:
+  addq    $8, %rsp                 # Restore stack
+  popq    %r12                     # Restore r12
+  popq    %rbx                     # Restore rbx
+  ret                              # Return from function
+
+.data
+.free_list:
+  .quad   0
+.heap_top:
+  .quad   0
+.heap_end:
+  .quad   0
@@ -0,0 +1,343 @@
+# Files are thin wrappers over the syscalls, stdin is read through a buffer,
+# that read_line, read_i64 and read_f64 share, so mixing them with read(0, ...) loses input.
+.set    STDIN_BUF_SIZE, 4096
+.set    PATH_MAX, 4096
+
+.text
+.globl open
+open:
+  pushq   %rbx                     # Save rbx, we keep the flags in it
+  subq    $PATH_MAX, %rsp          # Room for the path with a null terminator, keeps the stack aligned
+  movq    %rdx, %rbx               # Save flags
+  cmpq    $PATH_MAX, %rsi          # Test if the path fits, along with the terminator
+  jae     .open_too_long           # If not, fail like the kernel would
+  movq    %rsi, %rcx               # Length of the path
+  movq    %rdi, %rsi               # Copy from the path
+  movq    %rsp, %rdi               # Into the buffer
+  rep movsb                        # Copy
+  movb    $0, (%rdi)               # Terminate the path
+  movq    $420, %rcx               # Mode 0644, used when the file is created
+  movq    %rbx, %rdx               # Flags
+  movq    %rsp, %rsi               # Pointer to the path
+  movq    $2, %rdi                 # Syscall number for open
+  call    syscall3                 # Perform syscall
+  jmp     .open_done               # Return the file descriptor, or -errno
+  .open_too_long:
+  movq    $-36, %rax               # -ENAMETOOLONG
+  .open_done:
+  addq    $PATH_MAX, %rsp          # Restore stack
+  popq    %rbx                     # Restore rbx
+  ret                              # Return from function
+
+.globl read
+read:
+  movq    %rdx, %rcx               # Count
+  movq    %rsi, %rdx               # Buffer
+  movq    %rdi, %rsi               # File descriptor
+  xorl    %edi, %edi               # Syscall number for read
+  jmp     syscall3                 # Tail call
+
+.globl write
+write:
+  movq    %rdx, %rcx               # Count
+  movq    %rsi, %rdx               # Buffer
+  movq    %rdi, %rsi               # File descriptor
+  movq    $1, %rdi                 # Syscall number for write
+  jmp     syscall3                 # Tail call
+
+.globl close
+close:
+  movq    %rdi, %rsi               # File descriptor
+  movq    $3, %rdi                 # Syscall number for close
+  jmp     syscall1                 # Tail call
+
+# Returns the next byte of stdin in rax, or -1 at the end of the input.
+stdin_getc:
+  movq    .stdin_pos(%rip), %rax   # Position in the buffer
+  cmpq    .stdin_len(%rip), %rax   # Test if there's anything left in it
+  jb      .stdin_getc_buffered     # If so, take the next byte
+  subq    $8, %rsp                 # Keep the stack aligned
+  movq    $STDIN_BUF_SIZE, %rcx    # Count
+  leaq    .stdin_buf(%rip), %rdx   # Buffer
+  xorl    %esi, %esi               # Stdin
+  xorl    %edi, %edi               # Syscall number for read
+  call    syscall3                 # Perform syscall
+  addq    $8, %rsp                 # Restore stack
+  testq   %rax, %rax               # Errors count as the end of the input too
+  jle     .stdin_getc_eof          # If nothing was read, we're done
+  movq    %rax, .stdin_len(%rip)   # Amount of bytes in the buffer
+  xorl    %eax, %eax               # Start at the beginning
+  .stdin_getc_buffered:
+  leaq    .stdin_buf(%rip), %rdx   # Buffer
+  movzbl  (%rdx, %rax), %ecx       # Next byte
+  incq    %rax                     # Move past it
+  movq    %rax, .stdin_pos(%rip)   # Save position
+  movl    %ecx, %eax               # Return the byte
+  ret
+  .stdin_getc_eof:
+  movq    $-1, %rax                # Return -1
+  ret
+
+# Puts the byte returned by the last stdin_getc back.
+stdin_ungetc:
+  decq    .stdin_pos(%rip)         # Move back
+  ret
+
+.globl read_line
+read_line:
+  pushq   %rbx                     # Save rbx, we keep the buffer in it
+  pushq   %r12                     # Save r12, we keep its capacity in it
+  pushq   %r13                     # Save r13, we keep the length of the line in it
+  movq    %rdi, %rbx               # Save buffer
+  movq    %rsi, %r12               # Save capacity
+  xorl    %r13d, %r13d             # Nothing read yet
+  call    stdin_getc               # First byte
+  cmpq    $-1, %rax                # Test if we're at the end of the input
+  je      .read_line_eof           # If so, there's no line
+  .read_line_loop:
+  cmpq    $10, %rax                # Test for the end of the line
+  je      .read_line_done          # The newline isn't stored
+  cmpq    $-1, %rax                # Test for the end of the input
+  je      .read_line_done          # The last line may lack the newline
+  cmpq    %r12, %r13               # Test if the buffer is full
+  jae     .read_line_next          # If so, the rest of the line is dropped
+  movb    %al, (%rbx, %r13)        # Store the byte
+  incq    %r13                     # Count it
+  .read_line_next:
+  call    stdin_getc               # Next byte
+  jmp     .read_line_loop          # Keep reading
+  .read_line_done:
+  movq    %r13, %rax               # Return the length of the line
+  jmp     .read_line_ret
+  .read_line_eof:
+  movq    $-1, %rax                # Return -1
+  .read_line_ret:
+  popq    %r13                     # Restore r13
+  popq    %r12                     # Restore r12
+  popq    %rbx                     # Restore rbx
+  ret                              # Return from function
+
+# Both number readers store a status through the pointer in rdi, unless it's null:
+# 0 if a number was read, -1 at the end of the input, and -2 if the input isn't a number,
+# or doesn't fit, in which case the offending byte, or the whole run of digits, is dropped.
+.globl read_i64
+read_i64:
+  pushq   %rbx                     # Save rbx, we keep the value in it
+  pushq   %r12                     # Save r12, we keep the sign, then the status in it
+  pushq   %rdi                     # Save the pointer to the status, keeps the stack aligned
+  xorl    %ebx, %ebx               # Value
+  xorl    %r12d, %r12d             # Positive
+  .read_i64_skip:
+  call    stdin_getc               # Next byte
+  cmpq    $-1, %rax                # Test for the end of the input
+  je      .read_i64_eof            # If so, there's no number
+  cmpq    $32, %rax                # Everything up to the space is whitespace
+  jbe     .read_i64_skip           # Skip it
+  cmpq    $45, %rax                # Test for '-'
+  jne     .read_i64_plus           # If not, maybe there's a '+'
+  movq    $1, %r12                 # Negative
+  call    stdin_getc               # Next byte
+  jmp     .read_i64_first          # Parse the digits
+  .read_i64_plus:
+  cmpq    $43, %rax                # Test for '+'
+  jne     .read_i64_first          # If not, it's a digit already
+  call    stdin_getc               # Next byte
+  .read_i64_first:
+  leaq    -48(%rax), %rcx          # Value of the digit
+  cmpq    $9, %rcx                 # Test if it's a digit
+  ja      .read_i64_nan            # If not, it's not a number, and the byte is dropped
+  .read_i64_digits:
+  leaq    -48(%rax), %rcx          # Value of the digit
+  cmpq    $9, %rcx                 # Test if it's a digit
+  ja      .read_i64_end            # If not, the number has ended
+  imulq   $10, %rbx                # Shift the value by a digit
+  jo      .read_i64_overflow       # If it doesn't fit, the number is dropped
+  subq    %rcx, %rbx               # Subtract the digit, the value is kept negative, so the smallest one fits
+  jo      .read_i64_overflow       # If it doesn't fit, the number is dropped
+  call    stdin_getc               # Next byte
+  jmp     .read_i64_digits         # Keep parsing
+  .read_i64_end:
+  testq   %r12, %r12               # Test if the number is negative
+  jnz     .read_i64_ok             # If so, it's negative already
+  negq    %rbx                     # Otherwise make it positive
+  jo      .read_i64_too_big        # The smallest value has no positive counterpart
+  .read_i64_ok:
+  xorl    %r12d, %r12d             # A number was read
+  jmp     .read_i64_unget
+  .read_i64_overflow:
+  call    stdin_getc               # Next byte
+  leaq    -48(%rax), %rcx          # Value of the digit
+  cmpq    $9, %rcx                 # Test if it's a digit
+  jbe     .read_i64_overflow       # If so, drop it along with the rest
+  .read_i64_too_big:
+  xorl    %ebx, %ebx               # Return zero
+  movq    $-2, %r12                # The number doesn't fit
+  .read_i64_unget:
+  cmpq    $-1, %rax                # Test for the end of the input
+  je      .read_i64_done           # If so, there's nothing to put back
+  call    stdin_ungetc             # The byte after the number is left for the next read
+  jmp     .read_i64_done
+  .read_i64_nan:
+  movq    $-2, %r12                # Not a number
+  jmp     .read_i64_done
+  .read_i64_eof:
+  movq    $-1, %r12                # End of the input
+  .read_i64_done:
+  popq    %rcx                     # Pointer to the status
+  testq   %rcx, %rcx               # Test if it's null
+  jz      .read_i64_ret            # If so, the status isn't wanted
+  movq    %r12, (%rcx)             # Store the status
+  .read_i64_ret:
+  movq    %rbx, %rax               # Return the value
+  popq    %r12                     # Restore r12
+  popq    %rbx                     # Restore rbx
+  ret                              # Return from function
+
+.globl read_f64
+read_f64:
+  pushq   %rbx                     # Save rbx, we keep the digits in it
+  pushq   %r12                     # Save r12, we keep the sign in it
+  pushq   %r13                     # Save r13, we keep the decimal exponent in it
+  pushq   %r14                     # Save r14, we keep whether we're past the point, then the written exponent in it
+  pushq   %r15                     # Save r15, we keep the sign of the written exponent in it
+  pushq   %rdi                     # Save the pointer to the status
+  pushq   $-2                      # Status, not a number until there's a digit, keeps the stack aligned
+  xorl    %ebx, %ebx               # Digits
+  xorl    %r12d, %r12d             # Positive
+  xorl    %r13d, %r13d             # Decimal exponent
+  xorl    %r14d, %r14d             # Not past the point
+  .read_f64_skip:
+  call    stdin_getc               # Next byte
+  cmpq    $-1, %rax                # Test for the end of the input
+  je      .read_f64_eof            # If so, there's no number
+  cmpq    $32, %rax                # Everything up to the space is whitespace
+  jbe     .read_f64_skip           # Skip it
+  cmpq    $45, %rax                # Test for '-'
+  jne     .read_f64_plus           # If not, maybe there's a '+'
+  movq    $1, %r12                 # Negative
+  call    stdin_getc               # Next byte
+  jmp     .read_f64_digits         # Parse the digits
+  .read_f64_plus:
+  cmpq    $43, %rax                # Test for '+'
+  jne     .read_f64_digits         # If not, it's a digit already
+  call    stdin_getc               # Next byte
+  .read_f64_digits:
+  cmpq    $46, %rax                # Test for '.'
+  jne     .read_f64_digit          # If not, maybe it's a digit
+  testq   %r14, %r14               # Test if we're past the point already
+  jnz     .read_f64_exp            # If so, the number has ended
+  movq    $1, %r14                 # Past the point
+  call    stdin_getc               # Next byte
+  jmp     .read_f64_digits         # Keep parsing
+  .read_f64_digit:
+  leaq    -48(%rax), %rcx          # Value of the digit
+  cmpq    $9, %rcx                 # Test if it's a digit
+  ja      .read_f64_exp            # If not, maybe there's an exponent
+  movq    $0, (%rsp)               # There's a digit, so it's a number
+  movq    $100000000000000000, %rdx    # Digits that still fit
+  cmpq    %rdx, %rbx               # Test if there's room for another one
+  jae     .read_f64_drop           # If not, it's dropped
+  imulq   $10, %rbx                # Shift the digits
+  addq    %rcx, %rbx               # Add the digit
+  subq    %r14, %r13               # Digits after the point lower the exponent
+  jmp     .read_f64_next
+  .read_f64_drop:
+  cmpq    $1, %r14                 # Dropped digits before the point
+  adcq    $0, %r13                 # raise the exponent
+  .read_f64_next:
+  call    stdin_getc               # Next byte
+  jmp     .read_f64_digits         # Keep parsing
+  .read_f64_exp:
+  cmpq    $0, (%rsp)               # Test if there was a digit
+  jne     .read_f64_nan            # If not, it's not a number, and the byte is dropped
+  movq    %rax, %rcx               # Byte after the digits
+  orq     $32, %rcx                # Lowercase it
+  cmpq    $101, %rcx               # Test for 'e'
+  jne     .read_f64_end            # If not, the number has ended
+  xorl    %r14d, %r14d             # Written exponent
+  xorl    %r15d, %r15d             # Positive
+  call    stdin_getc               # Next byte
+  cmpq    $45, %rax                # Test for '-'
+  jne     .read_f64_exp_plus       # If not, maybe there's a '+'
+  movq    $1, %r15                 # Negative
+  call    stdin_getc               # Next byte
+  jmp     .read_f64_exp_digits     # Parse the digits
+  .read_f64_exp_plus:
+  cmpq    $43, %rax                # Test for '+'
+  jne     .read_f64_exp_digits     # If not, it's a digit already
+  call    stdin_getc               # Next byte
+  .read_f64_exp_digits:
+  leaq    -48(%rax), %rcx          # Value of the digit
+  cmpq    $9, %rcx                 # Test if it's a digit
+  ja      .read_f64_exp_end        # If not, the exponent has ended
+  cmpq    $100000, %r14            # Anything bigger overflows anyway
+  jae     .read_f64_exp_next       # So the digit is dropped
+  imulq   $10, %r14                # Shift the exponent
+  addq    %rcx, %r14               # Add the digit
+  .read_f64_exp_next:
+  call    stdin_getc               # Next byte
+  jmp     .read_f64_exp_digits     # Keep parsing
+  .read_f64_exp_end:
+  testq   %r15, %r15               # Test if the exponent is negative
+  jz      .read_f64_exp_add        # If not, add it as is
+  negq    %r14                     # Negate it
+  .read_f64_exp_add:
+  addq    %r14, %r13               # Add the written exponent
+  .read_f64_end:
+  cmpq    $-1, %rax                # Test for the end of the input
+  je      .read_f64_convert        # If so, there's nothing to put back
+  call    stdin_ungetc             # The byte after the number is left for the next read
+  .read_f64_convert:
+  cvtsi2sdq %rbx, %xmm0            # Digits as a float
+  movsd   .read_f64_ten(%rip), %xmm1   # Base of the exponent
+  .read_f64_scale_up:
+  testq   %r13, %r13               # Test if the exponent is positive
+  jle     .read_f64_scale_down     # If not, maybe it's negative
+  mulsd   %xmm1, %xmm0             # Multiply by ten
+  decq    %r13                     # One less to go
+  jmp     .read_f64_scale_up
+  .read_f64_scale_down:
+  testq   %r13, %r13               # Test if the exponent is negative
+  jge     .read_f64_sign           # If not, we're done scaling
+  divsd   %xmm1, %xmm0             # Divide by ten
+  incq    %r13                     # One less to go
+  jmp     .read_f64_scale_down
+  .read_f64_sign:
+  testq   %r12, %r12               # Test if the number is negative
+  jz      .read_f64_done           # If not, we're done
+  movq    %xmm0, %rax              # Bits of the value
+  btcq    $63, %rax                # Flip the sign bit
+  movq    %rax, %xmm0              # Return the negated value
+  jmp     .read_f64_done
+  .read_f64_eof:
+  movq    $-1, (%rsp)              # End of the input
+  .read_f64_nan:
+  xorpd   %xmm0, %xmm0             # Return zero
+  .read_f64_done:
+  popq    %rax                     # Status
+  popq    %rcx                     # Pointer to the status
+  testq   %rcx, %rcx               # Test if it's null
+  jz      .read_f64_ret            # If so, the status isn't wanted
+  movq    %rax, (%rcx)             # Store the status
+  .read_f64_ret:
+  popq    %r15                     # Restore r15
+  popq    %r14                     # Restore r14
+  popq    %r13                     # Restore r13
+  popq    %r12                     # Restore r12
+  popq    %rbx                     # Restore rbx
+  ret                              # Return from function
+
+.section .rodata
+.align 8
+.read_f64_ten:
+  .double 10.0
+
+.data
+.stdin_pos:
+  .quad   0
+.stdin_len:
+  .quad   0
+
+.bss
+.stdin_buf:
+  .zero   STDIN_BUF_SIZE
@@ -0,0 +1,38 @@
+.text
+.globl panic_bounds
+panic_bounds:
+  pushq   %rbx                     # Save rbx, we keep the index in it
+  pushq   %r12                     # Save r12, we keep the length in it
+  subq    $8, %rsp                 # Keep the stack aligned
+  movq    %rdx, %rbx               # Save index
+  movq    %rcx, %r12               # Save length
+  xorl    %edx, %edx               # No newline after the location
+  movl    $2, %ecx                 # Print to stderr
+  call    print_str                # Print the location, pointer and length are already in rdi and rsi
+  leaq    .panic_bounds_len(%rip), %rdi    # Pointer to the message
+  movq    $43, %rsi                # Length of the message
+  xorl    %edx, %edx               # No newline
+  movl    $2, %ecx                 # Print to stderr
+  call    print_str                # Print the message
+  movq    %r12, %rdi               # Length of the array
+  xorl    %esi, %esi               # No newline
+  movl    $2, %edx                 # Print to stderr
+  call    print_u64                # Print the length
+  leaq    .panic_bounds_idx(%rip), %rdi    # Pointer to the message
+  movq    $18, %rsi                # Length of the message
+  xorl    %edx, %edx               # No newline
+  movl    $2, %ecx                 # Print to stderr
+  call    print_str                # Print the message
+  movq    %rbx, %rdi               # Index, printed signed, so negative ones show up as such
+  movl    $1, %esi                 # Newline
+  movl    $2, %edx                 # Print to stderr
+  call    print_i64                # Print the index
+  movq    $60, %rdi                # Syscall number for exit
+  movq    $1, %rsi                 # Exit code
+  call    syscall1                 # Perform syscall, never returns
+
+.section .rodata
+.panic_bounds_len:
+  .ascii  " error: index out of bounds: the length is "
+.panic_bounds_idx:
+  .ascii  " but the index is "
@@ -0,0 +1,20 @@
+.text
+.globl print_bool
+print_bool:
+  movl    %edx, %ecx               # File descriptor
+  movl    %esi, %edx               # Newline flag
+  testl   %edi, %edi               # Test if the bool is zero
+  jz      .print_bool_false        # If zero, print false
+  leaq    .print_bool_true_str(%rip), %rdi   # Pointer to "true"
+  movq    $4, %rsi                 # Length of "true"
+  jmp     print_str                # Tail call print_str
+  .print_bool_false:
+  leaq    .print_bool_false_str(%rip), %rdi  # Pointer to "false"
+  movq    $5, %rsi                 # Length of "false"
+  jmp     print_str                # Tail call print_str
+
+.section .rodata
+.print_bool_true_str:
+  .ascii  "true"
+.print_bool_false_str:
+  .ascii  "false"
@@ -0,0 +1,15 @@
+.text
+.globl print_char
+print_char:
+  subq    $24, %rsp                # Room for the char and the newline, keeps the stack aligned
+  movb    %dil, (%rsp)             # Store the char
+  movb    $10, 1(%rsp)             # Store the newline right after it
+  movl    %esi, %ecx               # Newline flag
+  andl    $1, %ecx                 # Make sure it's either zero or one
+  incl    %ecx                     # Length, the newline is written along with the char
+  movslq  %edx, %rsi               # File descriptor
+  movq    %rsp, %rdx               # Pointer to the buffer
+  movq    $1, %rdi                 # Syscall number for write
+  call    syscall3                 # Perform syscall
+  addq    $24, %rsp                # Restore stack
+  ret                              # Return from function
@@ -0,0 +1,5 @@
+.text
+.globl print_f32
+print_f32:
+  cvtss2sd %xmm0, %xmm0            # Widen the float to a double
+  jmp     print_f64                # Tail call print_f64
@@ -4,128 +4,94 @@ print_f64:
   pushq   %rbp                     # Save base pointer
   movq    %rsp, %rbp               # Set up base pointer
   subq    $128, %rsp               # Allocate space on stack
-  mov     %rsi, %r15
-  movsd   %xmm0, -8(%rbp)          # Store the float in xmm0 into the stack
-  movl    $0, -52(%rbp)            # Initialize a local variable to 0
-  xorpd   %xmm0, %xmm0             # Zero xmm0
-  ucomisd -8(%rbp), %xmm0          # Compare xmm0 with the value at [rbp-8]
-  jbe     .LBB0_2                  # Jump if below or equal
-  movl    -52(%rbp), %eax          # Move local variable into eax
-  movl    %eax, %ecx               # Copy eax to ecx
-  incl    %ecx                     # Increment ecx
-  movl    %ecx, -52(%rbp)          # Update local variable
-  cltq                             # Sign-extend eax into rax
-  movb    $45, -48(%rbp,%rax)      # Store ASCII '-' at calculated stack location
-  movsd   -8(%rbp), %xmm0          # Load the double from stack into xmm0
-  movq    %xmm0, %rax              # Move the lower 64 bits of xmm0 into rax
-  movq    $-9223372036854775808, %rcx   # Move constant into rcx
-  xorq    %rcx, %rax               # XOR rax with rcx
+  movl    %edi, -4(%rbp)           # Store the newline flag
+  movl    %esi, -8(%rbp)           # Store the file descriptor
+  cmpl    $18, %edx                # 10^18 is the biggest power of ten that fits into i64
+  jle     .LBB0_1                  # Jump if the precision fits
+  movl    $18, %edx                # Clamp the precision
+  .LBB0_1:
+  movl    %edx, -12(%rbp)          # Store the precision
+  leaq    -128(%rbp), %rdi         # Output cursor
+  xorpd   %xmm1, %xmm1             # Zero xmm1
+  ucomisd %xmm0, %xmm1             # Compare 0 with the value in xmm0
+  jbe     .LBB0_2                  # Jump if the value is not negative
+  movb    $45, (%rdi)              # Store ASCII '-'
+  incq    %rdi                     # Advance the output cursor
+  movq    %xmm0, %rax              # Move the bits of the double into rax
+  btcq    $63, %rax                # Flip the sign bit
   movq    %rax, %xmm0              # Move rax back into xmm0
-  movsd   %xmm0, -8(%rbp)          # Store xmm0 back into the stack
   .LBB0_2:
-  cvttsd2si -8(%rbp), %eax         # Convert float to integer
-  movl    %eax, -56(%rbp)          # Store result in local variable
-  movsd   -8(%rbp), %xmm0          # Load double from stack
-  cvtsi2sd -56(%rbp), %xmm1        # Convert integer back to double
-  subsd   %xmm1, %xmm0             # Subtract converted value from original double
-  movsd   %xmm0, -64(%rbp)         # Store the fractional part
-  cmpl    $0, -56(%rbp)            # Compare the integer value with 0
-  jne     .LBB0_4                  # Jump if not equal
-  movl    -52(%rbp), %eax          # Load local variable into eax
-  movl    %eax, %ecx               # Copy eax to ecx
-  incl    %ecx                     # Increment ecx
-  movl    %ecx, -52(%rbp)          # Store the result back
-  cltq                             # Sign-extend eax into rax
-  movb    $48, -48(%rbp,%rax)      # Store ASCII '0' at calculated stack location
-  jmp     .LBB0_12                 # Jump to the end
+  movl    -12(%rbp), %ecx          # Loop counter = precision
+  movq    $1, %rax                 # scale = 1
+  .LBB0_3:
+  testl   %ecx, %ecx               # Compare loop counter with 0
+  jz      .LBB0_4                  # Jump if done
+  imulq   $10, %rax                # scale *= 10
+  decl    %ecx                     # Decrement loop counter
+  jmp     .LBB0_3                  # Repeat loop
   .LBB0_4:
-  movl    $0, -116(%rbp)           # Initialize another local variable
+  movq    %rax, %r8                # r8 = scale
+  cvtsi2sdq %rax, %xmm1            # xmm1 = scale as a double
+  movq    $0x3fe0000000000000, %rax    # Load constant (0.5 as double)
+  movq    %rax, %xmm2              # Move it into xmm2
+  divsd   %xmm1, %xmm2             # xmm2 = 0.5 / scale
+  addsd   %xmm2, %xmm0             # Round the value to the precision
+  cvttsd2siq %xmm0, %rax           # rax = integer part
+  cvtsi2sdq %rax, %xmm2            # Convert integer part back to double
+  subsd   %xmm2, %xmm0             # xmm0 = fractional part
+  mulsd   %xmm1, %xmm0             # Scale the fractional part up
+  cvttsd2siq %xmm0, %r9            # r9 = fractional digits as an integer
+  cmpq    %r8, %r9                 # Compare fractional digits with the scale
+  jl      .LBB0_5                  # Jump if they fit
+  leaq    -1(%r8), %r9             # Clamp to scale - 1
   .LBB0_5:
-  cmpl    $0, -56(%rbp)            # Compare integer with 0
-  jle     .LBB0_7                  # Jump if less or equal
-  movl    -56(%rbp), %eax          # Load the integer into eax
-  movl    $10, %ecx                # Move 10 into ecx
-  cltd                             # Sign-extend eax into edx:eax
-  idivl   %ecx                     # Divide edx:eax by 10
-  movl    -116(%rbp), %eax         # Load local variable
-  movl    %eax, %ecx               # Copy to ecx
-  incl    %ecx                     # Increment ecx
-  movl    %ecx, -116(%rbp)         # Store back the incremented value
-  cltq                             # Sign-extend eax into rax
-  movl    %edx, -112(%rbp,%rax,4)  # Store remainder in stack array
-  movl    %eax, -56(%rbp)          # Update quotient
-  jmp     .LBB0_5                  # Repeat loop
+  leaq    -32(%rbp), %r10          # End of the scratch area
+  movq    %r10, %rsi               # Scratch cursor, digits are produced backwards
+  movq    $10, %rcx                # Divisor
+  .LBB0_6:
+  xorq    %rdx, %rdx               # Zero the upper half of the dividend
+  divq    %rcx                     # rax = rax / 10, rdx = rax % 10
+  addb    $48, %dl                 # Convert to ASCII
+  decq    %rsi                     # Move the scratch cursor back
+  movb    %dl, (%rsi)              # Store ASCII digit
+  testq   %rax, %rax               # Compare quotient with 0
+  jnz     .LBB0_6                  # Repeat loop
   .LBB0_7:
-  movl    -116(%rbp), %eax         # Load the count
-  decl    %eax                     # Decrement eax
-  movl    %eax, -120(%rbp)         # Store back
+  movb    (%rsi), %dl              # Load digit from the scratch area
+  movb    %dl, (%rdi)              # Store it into the output
+  incq    %rsi                     # Advance the scratch cursor
+  incq    %rdi                     # Advance the output cursor
+  cmpq    %r10, %rsi               # Compare scratch cursor with its end
+  jl      .LBB0_7                  # Repeat loop
+  movl    -12(%rbp), %ecx          # Loop counter = precision
+  testl   %ecx, %ecx               # Compare precision with 0
+  jz      .LBB0_9                  # Jump if there is no fractional part to print
+  movb    $46, (%rdi)              # Store ASCII '.'
+  incq    %rdi                     # Advance the output cursor
+  addq    %rcx, %rdi               # Move the output cursor past the fractional digits
+  movq    %rdi, %rsi               # Fractional digits are produced backwards
+  movq    %r9, %rax                # rax = fractional digits
+  movq    $10, %r8                 # Divisor
   .LBB0_8:
-  cmpl    $0, -120(%rbp)           # Compare the count with 0
-  jl      .LBB0_11                 # Jump if less than 0
-  movslq  -120(%rbp), %rax         # Sign-extend local var to rax
-  movl    -112(%rbp,%rax,4), %eax  # Load the stored digit
-  addl    $48, %eax                # Convert to ASCII
-  movb    %al, %cl                 # Copy to cl
-  movl    -52(%rbp), %eax          # Load local variable into eax
-  movl    %eax, %edx               # Copy eax to edx
-  incl    %edx                     # Increment edx
-  movl    %edx, -52(%rbp)          # Store back the incremented value
-  cltq                             # Sign-extend eax into rax
-  movb    %cl, -48(%rbp,%rax)      # Store ASCII digit
-  decl    -120(%rbp)               # Decrement the counter
-  jmp     .LBB0_8                  # Repeat
-  .LBB0_11:
-  jmp     .LBB0_12                 # Continue
-  .LBB0_12:
-  movl    -52(%rbp), %eax          # Load local variable into eax
-  movl    %eax, %ecx               # Copy eax to ecx
-  incl    %ecx                     # Increment ecx
-  movl    %ecx, -52(%rbp)          # Store back
-  cltq                             # Sign-extend eax into rax
-  movb    $46, -48(%rbp,%rax)      # Store ASCII '.'
-  movl    $0, -124(%rbp)           # Initialize loop counter
-  .LBB0_13:
-  cmpl    $10, -124(%rbp)          # Compare loop counter with 10
-  jge     .LBB0_16                 # Jump if greater or equal
-  movq    $0x4024000000000000, %rax    # Load constant (10.0 as double)
-  movq    %rax, %xmm0              # Move it into xmm0
-  mulsd   -64(%rbp), %xmm0         # Multiply with the fractional part
-  movsd   %xmm0, -64(%rbp)         # Store the result
-  cvttsd2si -64(%rbp), %eax        # Convert fractional part to int
-  movl    %eax, -128(%rbp)         # Store it
-  movl    -128(%rbp), %eax         # Load the result
-  addl    $48, %eax                # Convert to ASCII
-  movb    %al, %cl                 # Copy to cl
-  movl    -52(%rbp), %eax          # Load local variable
-  movl    %eax, %edx               # Copy eax to edx
-  incl    %edx                     # Increment edx
-  movl    %edx, -52(%rbp)          # Store back the incremented value
-  cltq                             # Sign-extend eax into rax
-  movb    %cl, -48(%rbp,%rax)      # Store the ASCII digit
-  cvtsi2sd -128(%rbp), %xmm1       # Convert integer back to double
-  movsd   -64(%rbp), %xmm0         # Load fractional part
-  subsd   %xmm1, %xmm0             # Subtract integer part
-  movsd   %xmm0, -64(%rbp)         # Store remaining fractional part
-  incl    -124(%rbp)               # Increment loop counter
-  jmp     .LBB0_13                 # Repeat loop
-  .LBB0_16:
-  movl    -52(%rbp), %eax          # Load local variable
-  movl    %eax, %ecx               # Copy to ecx
-  incl    %ecx                     # Increment ecx
-  movl    %ecx, -52(%rbp)          # Store back
-  cltq                             # Sign-extend eax into rax
-  movb    $10, -48(%rbp,%rax)      # Store newline
-  leaq    -48(%rbp), %rsi          # Load address of string into rsi
-  movslq  -52(%rbp), %rdx          # Load string length
-  testb   %r15b, %r15b             # Test if r15b is zero
-  jz      .not_newline             # If zero, skip newline handling
-  jmp     .write                   # Otherwise, jump to write
-  .not_newline:
-  decq    %rdx                     # Decrement length
-  .write:
-  movq    $1, %rax                 # Syscall number for write
-  movq    $1, %rdi                 # File descriptor 1 (stdout)
-  syscall                          # Perform syscall
+  xorq    %rdx, %rdx               # Zero the upper half of the dividend
+  divq    %r8                      # rax = rax / 10, rdx = rax % 10
+  addb    $48, %dl                 # Convert to ASCII
+  decq    %rsi                     # Move the cursor back
+  movb    %dl, (%rsi)              # Store ASCII digit, leading zeros included
+  decl    %ecx                     # Decrement loop counter
+  jnz     .LBB0_8                  # Repeat loop
+  .LBB0_9:
+  cmpl    $0, -4(%rbp)             # Test the newline flag
+  je      .LBB0_10                 # If zero, skip the newline
+  movb    $10, (%rdi)              # Store newline
+  incq    %rdi                     # Advance the output cursor
+  .LBB0_10:
+  leaq    -128(%rbp), %rdx         # Load address of string into rdx
+  movq    %rdi, %rcx               # Load the output cursor
+  subq    %rdx, %rcx               # String length = cursor - start
+  movslq  -8(%rbp), %rsi           # File descriptor
+  movq    $1, %rdi                 # Syscall number for write
+  call    syscall3                 # Perform syscall
   addq    $128, %rsp               # Restore stack
   popq    %rbp                     # Restore base pointer
   ret                              # Return from function
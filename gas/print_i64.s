@@ -4,7 +4,8 @@ print_i64:
   pushq   %rbp                     # Save base pointer
   movq    %rsp, %rbp               # Set up base pointer
   subq    $64, %rsp                # Allocate space on stack
-  movq    %rsi, %r15
+  movl    %esi, -60(%rbp)          # Store the newline flag
+  movl    %edx, -64(%rbp)          # Store the file descriptor
   movq    %rdi, -8(%rbp)           # Store rdi on the stack
   movl    $0, -36(%rbp)            # Initialize local variable to 0
   movl    $0, -40(%rbp)            # Initialize local variable to 0
@@ -98,14 +99,14 @@ print_i64:
   movb    $10, -32(%rbp, %rax)     # Store newline character
   leaq    -32(%rbp), %rsi          # Load address of string into rsi
   movslq  -36(%rbp), %rdx          # Load string length
-  testb   %r15b, %r15b             # Test if r15b is zero
+  cmpl    $0, -60(%rbp)            # Test the newline flag
   jz      .not_newline             # If zero, skip newline handling
   jmp     .write                   # Otherwise, jump to write
   .not_newline:
   decq    %rdx                     # Decrement length
   .write:
   movq    $1, %rax                 # Syscall number for write
-  movq    $1, %rdi                 # File descriptor 1 (stdout)
+  movslq  -64(%rbp), %rdi          # File descriptor
   syscall                          # Perform syscall
   addq    $64, %rsp                # Restore stack
   popq    %rbp                     # Restore base pointer
@@ -0,0 +1,29 @@
+.text
+.globl print_str
+print_str:
+  pushq   %rbx                     # Save rbx, we keep the newline flag in it
+  pushq   %r12                     # Save r12, we keep the file descriptor in it
+  subq    $8, %rsp                 # Keep the stack aligned
+  movq    %rdx, %rbx               # Save newline flag
+  movslq  %ecx, %r12               # Save file descriptor
+  movq    %rsi, %rcx               # Length of the string
+  movq    %rdi, %rdx               # Pointer to the string
+  movq    %r12, %rsi               # File descriptor
+  movq    $1, %rdi                 # Syscall number for write
+  call    syscall3                 # Perform syscall
+  testb   %bl, %bl                 # Test if newline flag is zero
+  jz      .print_str_done          # If zero, skip the newline
+  leaq    .print_str_nl(%rip), %rdx    # Pointer to the newline
+  movq    $1, %rcx                 # Length of the newline
+  movq    %r12, %rsi               # File descriptor
+  movq    $1, %rdi                 # Syscall number for write
+  call    syscall3                 # Perform syscall
+  .print_str_done:
+  addq    $8, %rsp                 # Restore stack
+  popq    %r12                     # Restore r12
+  popq    %rbx                     # Restore rbx
+  ret                              # Return from function
+
+.section .rodata
+.print_str_nl:
+  .byte   10
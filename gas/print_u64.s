@@ -0,0 +1,32 @@
+.text
+.globl print_u64
+print_u64:
+  pushq   %rbp                     # Save base pointer
+  movq    %rsp, %rbp               # Set up base pointer
+  subq    $32, %rsp                # Allocate space on stack
+  movq    %rdi, %rax               # Value to print
+  movl    %edx, %r8d               # Save the file descriptor
+  movq    %rbp, %r10               # Cursor, the string is produced backwards from the end of the frame
+  testl   %esi, %esi               # Test the newline flag
+  jz      .LBB0_1                  # If zero, skip the newline
+  decq    %r10                     # Move the cursor back
+  movb    $10, (%r10)              # Store newline character
+  .LBB0_1:
+  movq    $10, %rcx                # Divisor
+  .LBB0_2:
+  xorq    %rdx, %rdx               # Zero the upper half of the dividend
+  divq    %rcx                     # rax = rax / 10, rdx = rax % 10
+  addb    $48, %dl                 # Convert to ASCII
+  decq    %r10                     # Move the cursor back
+  movb    %dl, (%r10)              # Store ASCII digit
+  testq   %rax, %rax               # Compare quotient with 0
+  jnz     .LBB0_2                  # Repeat loop
+  movq    %rbp, %rcx               # End of the string
+  subq    %r10, %rcx               # String length = end - cursor
+  movq    %r10, %rdx               # Load address of string into rdx
+  movslq  %r8d, %rsi               # File descriptor
+  movq    $1, %rdi                 # Syscall number for write
+  call    syscall3                 # Perform syscall
+  addq    $32, %rsp                # Restore stack
+  popq    %rbp                     # Restore base pointer
+  ret                              # Return from function
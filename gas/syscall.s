@@ -1,4 +1,10 @@
 .text
+.globl syscall0
+syscall0:
+  movq %rdi, %rax
+  syscall
+  ret
+
 .globl syscall1
 syscall1:
   movq %rdi, %rax
@@ -6,6 +12,14 @@ syscall1:
   syscall
   ret
 
+syscall2:
+  .globl syscall2
+  movq %rdi, %rax
+  movq %rsi, %rdi
+  movq %rdx, %rsi
+  syscall
+  ret
+
 syscall3:
   .globl syscall3
   movq %rdi, %rax
@@ -14,3 +28,36 @@ syscall3:
   movq %rcx, %rdx
   syscall
   ret
+
+syscall4:
+  .globl syscall4
+  movq %rdi, %rax
+  movq %rsi, %rdi
+  movq %rdx, %rsi
+  movq %rcx, %rdx
+  movq %r8, %r10
+  syscall
+  ret
+
+syscall5:
+  .globl syscall5
+  movq %rdi, %rax
+  movq %rsi, %rdi
+  movq %rdx, %rsi
+  movq %rcx, %rdx
+  movq %r8, %r10
+  movq %r9, %r8
+  syscall
+  ret
+
+syscall6:
+  .globl syscall6
+  movq %rdi, %rax
+  movq %rsi, %rdi
+  movq %rdx, %rsi
+  movq %rcx, %rdx
+  movq %r8, %r10
+  movq %r9, %r8
+  movq 8(%rsp), %r9
+  syscall
+  ret
@@ -1,26 +1,315 @@
-use crate::parser::{Ctx, SymMap};
+use crate::parser::{Ctx, SymKind, SymMap};
 use crate::lexer::{Loc, Token, TokenKind};
 
-#[derive(Debug, Clone)]
+use std::rc::Rc;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Type {
-    I64, F64
+    I8, I16, I32, I64,
+    U8, U16, U32, U64,
+    F32, F64, Str, Bool,
+    /// A single byte of text, only converts to and from integers with `as`.
+    Char,
+    /// Fixed-size array of scalars, lowered as the address of its first element.
+    Array(Box::<Type>, usize),
+    Ptr(Box::<Type>),
+    /// Like arrays, lowered as the address of the struct, and passed to functions as a QBE aggregate.
+    Struct(Rc::<StructDef>),
+    /// Tagged union, lowered like a struct: the tag, followed by the payload of the variant.
+    Enum(Rc::<EnumDef>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub ty: Type,
+    pub offset: usize,
+}
+
+/// Fields are laid out in order, each aligned to its own alignment, just like QBE lays out aggregates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructDef {
+    pub name: String,
+    pub fields: Vec::<Field>,
+    pub size: usize,
+    pub align: usize,
+}
+
+impl StructDef {
+    pub fn new(name: String, fields: Vec::<(String, Type)>) -> Self {
+        let mut size = 0_usize;
+        let mut align = 1;
+        let fields = fields.into_iter().map(|(name, ty)| {
+            let offset = size.next_multiple_of(ty.align());
+            size = offset + ty.size();
+            align = align.max(ty.align());
+            Field { name, ty, offset }
+        }).collect();
+        Self { name, fields, size: size.next_multiple_of(align), align }
+    }
+
+    #[inline]
+    pub fn field(&self, name: &str) -> Option::<&Field> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub name: String,
+    /// Types of the payload, along with their offsets from the start of the enum.
+    pub fields: Vec::<(Type, usize)>,
+}
+
+/// The tag is an `l` holding the index of the variant, the payload of every variant follows it, laid out like a struct.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDef {
+    pub name: String,
+    pub variants: Vec::<Variant>,
+    pub size: usize,
+    pub align: usize,
+}
+
+impl EnumDef {
+    pub const TAG_SIZE: usize = 8;
+
+    pub fn new(name: String, variants: Vec::<(String, Vec::<Type>)>) -> Self {
+        let mut size = Self::TAG_SIZE;
+        let mut align = Self::TAG_SIZE;
+        let variants = variants.into_iter().map(|(name, tys)| {
+            let mut end = Self::TAG_SIZE;
+            let fields = tys.into_iter().map(|ty| {
+                let offset = end.next_multiple_of(ty.align());
+                end = offset + ty.size();
+                align = align.max(ty.align());
+                (ty, offset)
+            }).collect();
+            size = size.max(end);
+            Variant { name, fields }
+        }).collect();
+        Self { name, variants, size: size.next_multiple_of(align), align }
+    }
+
+    /// Tag of the variant called `name`, along with the variant itself.
+    #[inline]
+    pub fn variant(&self, name: &str) -> Option::<(usize, &Variant)> {
+        self.variants.iter().enumerate().find(|(_, variant)| variant.name == name)
+    }
 }
 
 impl Type {
     pub fn try_from_token(t: &Token) -> Result::<Self, ()> {
         if t.kind != TokenKind::Type { return Err(()) }
-        match t.string {
-            "i64" => Ok(Self::I64),
-            "f64" => Ok(Self::F64),
-            _ => Err(())
+        Self::from_name(t.string).ok_or(())
+    }
+
+    pub fn from_name(name: &str) -> Option::<Self> {
+        match name {
+            "i8"  => Some(Self::I8),
+            "i16" => Some(Self::I16),
+            "i32" => Some(Self::I32),
+            "i64" => Some(Self::I64),
+            "u8"  => Some(Self::U8),
+            "u16" => Some(Self::U16),
+            "u32" => Some(Self::U32),
+            "u64" => Some(Self::U64),
+            "f32" => Some(Self::F32),
+            "f64" => Some(Self::F64),
+            "str" => Some(Self::Str),
+            "bool" => Some(Self::Bool),
+            "char" => Some(Self::Char),
+            _ => None
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_int(&self) -> bool {
+        matches!(self, Self::I8 | Self::I16 | Self::I32 | Self::I64 | Self::U8 | Self::U16 | Self::U32 | Self::U64)
+    }
+
+    #[inline(always)]
+    pub fn is_signed(&self) -> bool {
+        matches!(self, Self::I8 | Self::I16 | Self::I32 | Self::I64)
+    }
+
+    #[inline(always)]
+    pub fn is_float(&self) -> bool {
+        matches!(self, Self::F32 | Self::F64)
+    }
+
+    #[inline(always)]
+    pub fn is_numeric(&self) -> bool {
+        self.is_int() || self.is_float()
+    }
+
+    #[inline(always)]
+    pub fn is_ptr(&self) -> bool {
+        matches!(self, Self::Ptr(..))
+    }
+
+    /// Arrays, structs and enums, values of these live in memory, the operand is their address.
+    #[inline(always)]
+    pub fn is_aggregate(&self) -> bool {
+        matches!(self, Self::Array(..) | Self::Struct(..) | Self::Enum(..))
+    }
+
+    /// Arrays, structs and payloads of enums only hold scalars, pointers, structs and enums.
+    #[inline(always)]
+    pub fn is_elem(&self) -> bool {
+        !matches!(self, Self::Str | Self::Array(..))
+    }
+
+    /// Size of a value of the type in bytes, `str`s are counted as the pointer only.
+    #[inline]
+    pub fn size(&self) -> usize {
+        match self {
+            Self::I8  | Self::U8  | Self::Bool | Self::Char => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::I64 | Self::U64 | Self::F64 | Self::Str | Self::Ptr(..) => 8,
+            Self::Array(elem, len) => elem.size() * len,
+            Self::Struct(def) => def.size,
+            Self::Enum(def) => def.size,
+        }
+    }
+
+    #[inline]
+    pub fn align(&self) -> usize {
+        match self {
+            Self::Array(elem, _) => elem.align(),
+            Self::Struct(def) => def.align,
+            Self::Enum(def) => def.align,
+            _ => self.size(),
+        }
+    }
+
+    /// Inclusive range of values of an integer type.
+    pub fn int_range(&self) -> (i128, i128) {
+        let bits = self.size() as u32 * 8;
+        if self.is_signed() {
+            (-(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+        } else {
+            (0, (1 << bits) - 1)
+        }
+    }
+
+    /// Type of an unsuffixed integer literal on its own: `i64`, unless it only fits into a `u64`.
+    #[inline]
+    pub fn of_int_lit(v: i128) -> Self {
+        if v > i64::MAX as i128 { Self::U64 } else { Self::I64 }
+    }
+
+    #[inline]
+    pub fn fits(&self, v: i128) -> bool {
+        let (min, max) = self.int_range();
+        min <= v && v <= max
+    }
+
+    /// Truncates `v` to the width of the integer type, returning the bits as they're stored in an `l`.
+    pub fn wrap(&self, v: i128) -> i64 {
+        match self {
+            Self::I8  => v as i8  as _,
+            Self::I16 => v as i16 as _,
+            Self::I32 => v as i32 as _,
+            Self::U8  | Self::Char => v as u8 as _,
+            Self::U16 => v as u16 as _,
+            Self::U32 => v as u32 as _,
+            _ => v as i64
+        }
+    }
+
+    /// Whether every value of `from` is representable in `to`.
+    pub fn is_lossless(from: &Self, to: &Self) -> bool {
+        match (from.is_signed(), to.is_signed()) {
+            _ if from == to => true,
+            _ if *from == Self::F32 && *to == Self::F64 => true,
+            // the integer has to fit into the mantissa
+            _ if from.is_int() && *to == Self::F32 => from.size() <= 2,
+            _ if from.is_int() && *to == Self::F64 => from.size() <= 4,
+            _ if !from.is_int() || !to.is_int() => false,
+            (true, true) | (false, false) => to.size() >= from.size(),
+            (false, true) => to.size() > from.size(),
+            (true, false) => false,
+        }
+    }
+
+    /// Whether an unsuffixed literal of type `lit` may take type `ty`:
+    /// integer literals adapt to any number, float literals to floats only, and `null` to any pointer.
+    #[inline]
+    pub fn adapts(lit: &Self, ty: &Self) -> bool {
+        if lit.is_int() {
+            ty.is_numeric()
+        } else if lit.is_ptr() {
+            ty.is_ptr()
+        } else {
+            lit.is_float() && ty.is_float()
+        }
+    }
+
+    /// Conversions `as` allows: between any numbers, from bools to integers, between chars and integers,
+    /// and from pointers to 64-bit integers.
+    #[inline]
+    pub fn is_castable(from: &Self, to: &Self) -> bool {
+        from == to || from.is_numeric() && to.is_numeric() || *from == Self::Bool && to.is_int() ||
+            *from == Self::Char && to.is_int() || from.is_int() && *to == Self::Char ||
+            from.is_ptr() && matches!(to, Self::I64 | Self::U64)
+    }
+
+    /// Type both operands of a binary operator are converted to: ints are widened losslessly,
+    /// to floats too if the other operand is one, any lossy mix is rejected.
+    pub fn promote(lhs: &Self, rhs: &Self) -> Option::<Self> {
+        match (lhs, rhs) {
+            _ if lhs == rhs => Some(lhs.to_owned()),
+            _ if Self::is_lossless(lhs, rhs) => Some(rhs.to_owned()),
+            _ if Self::is_lossless(rhs, lhs) => Some(lhs.to_owned()),
+            _ => None
+        }
+    }
+}
+
+impl Display for Type {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::I8  => write!(f, "i8"),
+            Self::I16 => write!(f, "i16"),
+            Self::I32 => write!(f, "i32"),
+            Self::I64 => write!(f, "i64"),
+            Self::U8  => write!(f, "u8"),
+            Self::U16 => write!(f, "u16"),
+            Self::U32 => write!(f, "u32"),
+            Self::U64 => write!(f, "u64"),
+            Self::F32 => write!(f, "f32"),
+            Self::F64 => write!(f, "f64"),
+            Self::Str => write!(f, "str"),
+            Self::Bool => write!(f, "bool"),
+            Self::Char => write!(f, "char"),
+            Self::Array(elem, len) => write!(f, "{elem}[{len}]"),
+            Self::Ptr(ty) => write!(f, "{ty}*"),
+            Self::Struct(def) => write!(f, "{name}", name = def.name),
+            Self::Enum(def) => write!(f, "{name}", name = def.name),
         }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct VarDecl<'a> {
+    pub ty: Type,
+    /// Declared at the top level, so it's emitted as `data` rather than a temporary.
+    pub global: bool,
+    /// Its address is taken somewhere, so it lives in a stack slot rather than a temporary.
+    pub stack: bool,
+    pub il_name: String,
+    /// Only aggregates may be declared without an initializer, they're zeroed then.
+    pub value: Option::<Box::<Expr<'a>>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Assign<'a> {
+    /// A variable `x`, an element of an array `xs[i]`, a field `p.x`, or a dereferenced pointer `*p`.
+    pub target: Box::<Expr<'a>>,
     pub value: Box::<Expr<'a>>,
-    pub name_token: Box::<Token<'a>>,
 }
 
 #[derive(Debug, Clone)]
@@ -30,10 +319,15 @@ pub struct FnCall<'a> {
 }
 
 impl Type {
+    /// `str` is lowered as a pair of `l`s: the pointer to the data and its length.
+    /// Integers narrower than 32 bits live in `w`s, kept sign or zero extended from their width.
     pub fn to_il_str(&self) -> &'static str {
         match self {
-            Self::I64 => "l",
+            Self::I64 | Self::U64 => "l",
+            Self::F32 => "s",
             Self::F64 => "d",
+            Self::Str | Self::Array(..) | Self::Ptr(..) | Self::Struct(..) | Self::Enum(..) => "l",
+            _ => "w",
         }
     }
 }
@@ -41,9 +335,40 @@ impl Type {
 #[derive(Debug, Clone)]
 pub struct FnArg<'a> {
     pub ty: Type,
+    /// Its address is taken in the body, so it's copied into a stack slot on entry.
+    pub stack: bool,
     pub name_token: Box::<Token<'a>>,
 }
 
+/// Everything about a function, that a call to it needs to know.
+#[derive(Debug, Clone)]
+pub struct FnSig<'a> {
+    pub ret_ty: Option::<Type>,
+    pub args: Vec::<FnArg<'a>>,
+    pub name_token: Box::<Token<'a>>,
+}
+
+impl<'a> FnSig<'a> {
+    /// Checks the arguments of a call, each along with the location it starts at, converting them to the parameter types.
+    pub fn check_args(&self, args: Vec::<(Box::<Expr<'a>>, Box::<Loc>)>, loc: &Loc, sym_map: &SymMap) -> Vec::<Box::<Expr<'a>>> {
+        let name = self.name_token.string;
+        if args.len() != self.args.len() {
+            panic!("{loc} error: `{name}` takes {n} argument{s}, but {m} {were} given\n{def_loc} note: `{name}` is defined here",
+                   n = self.args.len(), m = args.len(),
+                   s = if self.args.len() == 1 { "" } else { "s" },
+                   were = if args.len() == 1 { "was" } else { "were" },
+                   def_loc = self.name_token.loc)
+        }
+
+        args.into_iter().zip(self.args.iter()).enumerate().map(|(idx, ((expr, arg_loc), arg))| {
+            expr.coerce(&arg.ty, sym_map, &arg_loc).unwrap_or_else(|err| {
+                panic!("{arg_loc} error: argument {n} of `{name}`: {err}\n{decl_loc} note: parameter `{arg_name}` is declared here",
+                       n = idx + 1, decl_loc = arg.name_token.loc, arg_name = arg.name_token.string)
+            })
+        }).collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Fn<'a> {
     pub ret_ty: Option::<Type>,
@@ -52,24 +377,57 @@ pub struct Fn<'a> {
     pub name_token: Box::<Token<'a>>,
 }
 
+#[derive(Debug, Clone)]
+pub struct MatchArm<'a> {
+    /// Tag of the variant, `None` for the `_` arm.
+    pub variant: Option::<usize>,
+    /// Declared in the scope of the arm, initialized from the payload, `None` where the pattern is `_`.
+    pub bindings: Vec::<Option::<VarDecl<'a>>>,
+    pub body: Vec::<Box::<Ast<'a>>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Match<'a> {
+    pub value: Box::<Expr<'a>>,
+    pub def: Rc::<EnumDef>,
+    pub arms: Vec::<MatchArm<'a>>,
+}
+
 #[derive(Debug, Clone)]
 pub enum AstKind<'a> {
     Fn(Box::<Fn<'a>>),
     FnCall(Box::<FnCall<'a>>),
-    VarDecl(Box::<VarDecl<'a>>)
+    VarDecl(Box::<VarDecl<'a>>),
+    Assign(Box::<Assign<'a>>),
+    Block(Vec::<Box::<Ast<'a>>>),
+    Struct(Rc::<StructDef>),
+    Enum(Rc::<EnumDef>),
+    Match(Box::<Match<'a>>),
+    /// `return;` or `return value;`, the value is converted to the return type already.
+    Return(Option::<Box::<Expr<'a>>>),
 }
 
 #[derive(Debug, Clone)]
 pub struct Ast<'a> {
-    pub id: usize,
     pub ctx: Box::<Ctx<'a>>,
     pub loc: Box::<Loc>,
     pub kind: AstKind<'a>,
-    pub next: usize,
+}
+
+impl<'a> Ast<'a> {
+    /// Anything at the top level that isn't a definition, in a script these make up the body of the implicit `main`.
+    /// Globals with a runtime initializer are both: the `data` is defined, and the initializer is a statement.
+    #[inline]
+    pub fn is_stmt(&self) -> bool {
+        match &self.kind {
+            AstKind::Fn(..) | AstKind::Struct(..) | AstKind::Enum(..) => false,
+            AstKind::VarDecl(vd) => vd.value.as_ref().is_some_and(|value| !value.is_const()),
+            _ => true
+        }
+    }
 }
 
 pub struct Asts<'a> {
-    pub id: usize,
     pub asts: Vec::<Box::<Ast<'a>>>,
 }
 
@@ -79,73 +437,774 @@ impl<'a> Asts<'a> {
     #[inline]
     pub fn new() -> Self {
         Self {
-            id: 0,
             asts: Vec::with_capacity(Self::RESERVE),
         }
     }
 
-    #[inline(always)]
-    pub fn new_ast<'b>(&self, ctx: Box::<Ctx<'b>>, loc: Box::<Loc>, kind: AstKind<'b>) -> Ast<'b> {
-        Ast {ctx, id: self.id, next: self.id + 1, loc, kind}
-    }
-
-    #[inline(always)]
-    pub fn id(&self, id: usize) -> &Ast {
-        unsafe { self.asts.get_unchecked(id) }
-    }
-
-    #[inline(always)]
-    pub fn append_ast(&mut self, ast: Box::<Ast<'a>>) {
-        self.asts.push(ast);
-    }
-
     #[inline(always)]
     pub fn append(&mut self, ctx: Box::<Ctx<'a>>, loc: Box::<Loc>, kind: AstKind<'a>) {
-        let ast = Ast {ctx, id: self.id, next: self.id + 1, loc, kind};
+        let ast = Ast {ctx, loc, kind};
         self.asts.push(Box::new(ast));
-        self.id += 1;
     }
 }
 
+/// Kinds of parameters of the builtins implemented by the runtime.
+enum BuiltinParam {
+    /// Any integer, passed as an `l`.
+    Int,
+    /// Any pointer.
+    Ptr,
+    /// Passed as the pointer and the length.
+    Str,
+    /// A pointer, or a `str` passed as the pointer to its data.
+    Buf,
+    /// An `i64*` the runtime stores a status through, `null` if it isn't wanted.
+    Status,
+}
+
 #[derive(Debug, Clone)]
 pub enum Expr<'a> {
-    I64(i64),
+    /// Unsuffixed integer literal: `i64`, unless the other operand makes it adapt to its type.
+    /// Kept in `i128`, so it's range-checked against the type it takes, rather than against `i64` right away.
+    I64(i128),
+    /// Integer constant of a concrete type, with its bits stored as in an `l`.
+    Int(i64, Type),
+    /// Unsuffixed float literal: `f64`, unless the other operand is an `f32`.
     F64(f64),
+    /// Float constant of a concrete type, `f32`s are already rounded to single precision.
+    Flt(f64, Type),
+    Str(Vec::<u8>),
+    Bool(bool),
+    Char(u8),
     Lit(Box::<Token<'a>>),
     Add(Box::<Expr<'a>>, Box::<Expr<'a>>),
     Sub(Box::<Expr<'a>>, Box::<Expr<'a>>),
     Mul(Box::<Expr<'a>>, Box::<Expr<'a>>),
     Div(Box::<Expr<'a>>, Box::<Expr<'a>>),
+    Rem(Box::<Expr<'a>>, Box::<Expr<'a>>),
+    BitAnd(Box::<Expr<'a>>, Box::<Expr<'a>>),
+    BitOr(Box::<Expr<'a>>, Box::<Expr<'a>>),
+    BitXor(Box::<Expr<'a>>, Box::<Expr<'a>>),
+    Shl(Box::<Expr<'a>>, Box::<Expr<'a>>),
+    Shr(Box::<Expr<'a>>, Box::<Expr<'a>>),
+    Eq(Box::<Expr<'a>>, Box::<Expr<'a>>),
+    Ne(Box::<Expr<'a>>, Box::<Expr<'a>>),
+    Lt(Box::<Expr<'a>>, Box::<Expr<'a>>),
+    Le(Box::<Expr<'a>>, Box::<Expr<'a>>),
+    Gt(Box::<Expr<'a>>, Box::<Expr<'a>>),
+    Ge(Box::<Expr<'a>>, Box::<Expr<'a>>),
+    And(Box::<Expr<'a>>, Box::<Expr<'a>>),
+    Or(Box::<Expr<'a>>, Box::<Expr<'a>>),
+    Not(Box::<Expr<'a>>),
+    Neg(Box::<Expr<'a>>),
+    BitNot(Box::<Expr<'a>>),
+    Cast(Box::<Expr<'a>>, Type),
+    /// `[a, b, ...]`, the elements take the type of the array it initializes.
+    Array(Vec::<Box::<Expr<'a>>>),
+    /// `xs[i]`, the location is the one reported when the index is out of bounds at runtime.
+    Index(Box::<Expr<'a>>, Box::<Expr<'a>>, Box::<Loc>),
+    /// A flexible `u8*`, like unsuffixed literals it adapts to any other pointer type.
+    Null,
+    /// `&x`, `&xs[i]` or `&p.x`.
+    AddrOf(Box::<Expr<'a>>),
+    Deref(Box::<Expr<'a>>),
+    /// `Point { x: 1.0, y: 2.0 }`, with the name of the struct, and its fields in the order they're written in.
+    Struct(Box::<Token<'a>>, Vec::<(Box::<Token<'a>>, Box::<Expr<'a>>)>),
+    /// `p.x`
+    Field(Box::<Expr<'a>>, Box::<Token<'a>>),
+    /// `Shape.Circle(1.0)` or `Shape.Empty`, with the names of the enum and the variant.
+    Variant(Box::<Token<'a>>, Box::<Token<'a>>, Vec::<Box::<Expr<'a>>>),
+    /// Call to a builtin returning a value, e.g. `alloc(n)`.
+    Call(Box::<Token<'a>>, Vec::<Box::<Expr<'a>>>),
+    /// Call to a function of the file, with its return type, the arguments are converted to the parameter types already.
+    UserCall(Box::<Token<'a>>, Vec::<Box::<Expr<'a>>>, Type),
 }
 
 impl<'a> Expr<'a> {
-    pub fn eval_int(&self, sym_map: &SymMap) -> i64 {
+    /// Constant expressions don't reference any symbols, so they can be folded at parse time.
+    pub fn is_const(&self) -> bool {
+        match self {
+            Expr::I64(..) | Expr::Int(..) | Expr::F64(..) | Expr::Flt(..) | Expr::Str(..) | Expr::Bool(..) | Expr::Char(..) | Expr::Null => true,
+            Expr::Lit(..) | Expr::Index(..) | Expr::AddrOf(..) | Expr::Deref(..) => false,
+            Expr::Array(items) => items.iter().all(|item| item.is_const()),
+            Expr::Struct(_, fields) => fields.iter().all(|(_, value)| value.is_const()),
+            Expr::Field(..) => false,
+            Expr::Variant(_, _, args) => args.iter().all(|arg| arg.is_const()),
+            Expr::Call(..) | Expr::UserCall(..) => false,
+            Expr::Add(lhs, rhs) | Expr::Sub(lhs, rhs) |
+            Expr::Mul(lhs, rhs) | Expr::Div(lhs, rhs) |
+            Expr::Rem(lhs, rhs) | Expr::BitAnd(lhs, rhs) |
+            Expr::BitOr(lhs, rhs) | Expr::BitXor(lhs, rhs) |
+            Expr::Shl(lhs, rhs) | Expr::Shr(lhs, rhs) |
+            Expr::Eq(lhs, rhs)  | Expr::Ne(lhs, rhs)  |
+            Expr::Lt(lhs, rhs)  | Expr::Le(lhs, rhs)  |
+            Expr::Gt(lhs, rhs)  | Expr::Ge(lhs, rhs)  |
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs)  => lhs.is_const() && rhs.is_const(),
+            Expr::Not(expr) | Expr::Neg(expr) | Expr::BitNot(expr) | Expr::Cast(expr, _) => expr.is_const(),
+        }
+    }
+
+    /// Whether the expression refers to memory, that can be assigned to, or have its address taken.
+    pub fn is_place(&self) -> bool {
+        match self {
+            Expr::Lit(..) | Expr::Deref(..) => true,
+            Expr::Index(base, ..) | Expr::Field(base, _) => base.is_place(),
+            _ => false
+        }
+    }
+
+    /// Infers the type of the expression, reporting mismatched operands at `loc`.
+    #[inline]
+    pub fn type_check(&self, sym_map: &SymMap, loc: &Loc) -> Type {
+        self.infer(sym_map).unwrap_or_else(|err| panic!("{loc} error: {err}")).0
+    }
+
+    /// Type of the expression, along with whether it's built from unsuffixed literals only.
+    /// Such expressions adapt to the type of the other operand, see [`Expr::unify`].
+    pub fn infer(&self, sym_map: &SymMap) -> Result::<(Type, bool), String> {
+        let ret = match self {
+            Expr::I64(ival)   => (Type::of_int_lit(*ival), true),
+            Expr::Int(_, ty)  => (ty.to_owned(), false),
+            Expr::F64(..)     => (Type::F64, true),
+            Expr::Flt(_, ty)  => (ty.to_owned(), false),
+            Expr::Str(..)     => (Type::Str, false),
+            Expr::Bool(..)    => (Type::Bool, false),
+            Expr::Char(..)    => (Type::Char, false),
+            Expr::Lit(lit) => match sym_map.get(lit.string) {
+                Some(sym) if matches!(sym.kind, SymKind::Type) => return Err(format!("`{name}` is a type, not a value", name = lit.string)),
+                Some(sym) => (sym.ty.to_owned(), false),
+                None => panic!("{loc} error: undefined symbol: {string}", loc = lit.loc, string = lit.string)
+            }
+            Expr::Add(lhs, rhs) | Expr::Sub(lhs, rhs) |
+            Expr::Mul(lhs, rhs) | Expr::Div(lhs, rhs) => {
+                let (lty, _) = lhs.infer(sym_map)?;
+                let (rty, _) = rhs.infer(sym_map)?;
+                // pointers move by whole elements, and subtracting two of them gives the distance in elements
+                match (self, &lty, &rty) {
+                    (Expr::Add(..) | Expr::Sub(..), Type::Ptr(..), _) if rty.is_int() => return Ok((lty, false)),
+                    (Expr::Add(..), _, Type::Ptr(..)) if lty.is_int() => return Ok((rty, false)),
+                    (Expr::Sub(..), Type::Ptr(..), Type::Ptr(..)) if lty == rty => return Ok((Type::I64, false)),
+                    _ => {}
+                }
+                if !lty.is_numeric() || !rty.is_numeric() {
+                    return Err(format!("arithmetic is only defined for numbers, but got: {lty} and {rty}"))
+                }
+                if let Expr::Div(..) = self {
+                    Self::check_divisor(rhs, &rty, sym_map)?;
+                }
+                Self::unify(lhs, rhs, sym_map)?
+            }
+            Expr::Rem(lhs, rhs) | Expr::BitAnd(lhs, rhs) |
+            Expr::BitOr(lhs, rhs) | Expr::BitXor(lhs, rhs) |
+            Expr::Shl(lhs, rhs) | Expr::Shr(lhs, rhs) => {
+                let (lty, _) = lhs.infer(sym_map)?;
+                let (rty, _) = rhs.infer(sym_map)?;
+                if !lty.is_int() || !rty.is_int() {
+                    return Err(format!("`%`, bitwise and shift operators are only defined for integers, but got: {lty} and {rty}"))
+                }
+                if let Expr::Rem(..) = self {
+                    Self::check_divisor(rhs, &rty, sym_map)?;
+                }
+                Self::unify(lhs, rhs, sym_map)?
+            }
+            Expr::Eq(lhs, rhs) | Expr::Ne(lhs, rhs) => {
+                let (ty, _) = Self::unify(lhs, rhs, sym_map)?;
+                if matches!(ty, Type::Str | Type::Array(..) | Type::Struct(..) | Type::Enum(..)) {
+                    return Err(format!("can't compare {ty} with {ty}"))
+                }
+                (Type::Bool, false)
+            }
+            Expr::Lt(lhs, rhs) | Expr::Le(lhs, rhs) |
+            Expr::Gt(lhs, rhs) | Expr::Ge(lhs, rhs) => {
+                let (ty, _) = Self::unify(lhs, rhs, sym_map)?;
+                if !ty.is_numeric() && ty != Type::Char && !ty.is_ptr() {
+                    return Err(format!("ordering is only defined for numbers, chars and pointers, but got: {ty} and {ty}"))
+                }
+                (Type::Bool, false)
+            }
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                let (lty, _) = lhs.infer(sym_map)?;
+                let (rty, _) = rhs.infer(sym_map)?;
+                if lty != Type::Bool || rty != Type::Bool {
+                    return Err(format!("logical operators expect bool operands, but got: {lty} and {rty}"))
+                }
+                (Type::Bool, false)
+            }
+            Expr::Not(expr) => {
+                let (ty, _) = expr.infer(sym_map)?;
+                if ty != Type::Bool {
+                    return Err(format!("`!` expects a bool operand, but got: {ty}"))
+                }
+                (Type::Bool, false)
+            }
+            Expr::Neg(expr) => {
+                let (ty, flex) = expr.infer(sym_map)?;
+                if !ty.is_numeric() {
+                    return Err(format!("`-` expects a numeric operand, but got: {ty}"))
+                }
+                if ty.is_int() && !ty.is_signed() {
+                    return Err(format!("can't negate a value of unsigned type {ty}"))
+                }
+                (ty, flex)
+            }
+            Expr::BitNot(expr) => {
+                let (ty, flex) = expr.infer(sym_map)?;
+                if !ty.is_int() {
+                    return Err(format!("`~` expects an integer operand, but got: {ty}"))
+                }
+                (ty, flex)
+            }
+            Expr::Cast(expr, ty) => {
+                let (from, _) = expr.infer(sym_map)?;
+                if !Type::is_castable(&from, ty) {
+                    return Err(format!("can't cast {from} as {ty}"))
+                }
+                (ty.to_owned(), false)
+            }
+            Expr::Array(items) => {
+                let Some(first) = items.first() else {
+                    return Err("empty array literal, declare the array without an initializer instead".to_owned())
+                };
+                // the elements are promoted to a common type, literals only stay flexible if all of them are
+                let (mut ty, mut flex) = first.infer(sym_map)?;
+                for item in items[1..].iter() {
+                    let (ity, iflex) = item.infer(sym_map)?;
+                    ty = match (flex, iflex) {
+                        (true, false) if Type::adapts(&ty, &ity) => ity,
+                        (false, true) if Type::adapts(&ity, &ty) => ty,
+                        _ => Type::promote(&ty, &ity).ok_or_else(|| format!("mismatched array element types: {ty} and {ity}"))?
+                    };
+                    flex &= iflex;
+                }
+                if !ty.is_elem() {
+                    return Err(format!("arrays of {ty} aren't supported"))
+                }
+                (Type::Array(Box::new(ty), items.len()), false)
+            }
+            Expr::Index(base, index, _) => {
+                let (bty, _) = base.infer(sym_map)?;
+                let Type::Array(elem, len) = bty else {
+                    return Err(format!("only arrays can be indexed, but got: {bty}"))
+                };
+                let (ity, _) = index.infer(sym_map)?;
+                if !ity.is_int() {
+                    return Err(format!("array index must be an integer, but got: {ity}"))
+                }
+                if index.is_const() {
+                    let i = index.eval_int(sym_map);
+                    if i < 0 || i >= len as i128 {
+                        return Err(format!("index out of bounds: the length is {len} but the index is {i}"))
+                    }
+                }
+                (*elem, false)
+            }
+            Expr::Null => (Type::Ptr(Box::new(Type::U8)), true),
+            Expr::AddrOf(expr) => {
+                let (ty, _) = expr.infer(sym_map)?;
+                match **expr {
+                    _ if matches!(ty, Type::Array(..)) => {
+                        return Err("can't take the address of an array, take the address of its element instead, e.g. `&xs[0]`".to_owned())
+                    }
+                    _ if ty == Type::Str => return Err("can't take the address of a str".to_owned()),
+                    _ if expr.is_place() => (Type::Ptr(Box::new(ty)), false),
+                    _ => return Err("can only take the address of variables, elements of arrays and fields".to_owned())
+                }
+            }
+            Expr::Deref(expr) => {
+                let (ty, _) = expr.infer(sym_map)?;
+                let Type::Ptr(ty) = ty else {
+                    return Err(format!("only pointers can be dereferenced, but got: {ty}"))
+                };
+                (*ty, false)
+            }
+            Expr::Struct(name_token, fields) => {
+                let name = name_token.string;
+                let Some(Type::Struct(def)) = sym_map.get(name).map(|sym| &sym.ty) else {
+                    return Err(format!("`{name}` is not a struct"))
+                };
+                for (idx, (field_token, value)) in fields.iter().enumerate() {
+                    let fname = field_token.string;
+                    let Some(field) = def.field(fname) else {
+                        return Err(format!("struct `{name}` has no field `{fname}`"))
+                    };
+                    if fields[..idx].iter().any(|(prev, _)| prev.string == fname) {
+                        return Err(format!("field `{fname}` is initialized twice"))
+                    }
+                    value.check_assign(&field.ty, sym_map).map_err(|err| format!("field `{fname}` of `{name}`: {err}"))?;
+                }
+                if let Some(field) = def.fields.iter().find(|field| fields.iter().all(|(t, _)| t.string != field.name)) {
+                    return Err(format!("missing field `{fname}` in the literal of `{name}`", fname = field.name))
+                }
+                (Type::Struct(def.to_owned()), false)
+            }
+            Expr::Field(base, field_token) => {
+                let (ty, _) = base.infer(sym_map)?;
+                let Type::Struct(ref def) = ty else {
+                    return Err(format!("only structs have fields, but got: {ty}"))
+                };
+                let Some(field) = def.field(field_token.string) else {
+                    return Err(format!("struct `{ty}` has no field `{fname}`", fname = field_token.string))
+                };
+                (field.ty.to_owned(), false)
+            }
+            Expr::Variant(name_token, variant_token, args) => {
+                let name = name_token.string;
+                let Some(Type::Enum(def)) = sym_map.get(name).map(|sym| &sym.ty) else {
+                    return Err(format!("`{name}` is not an enum"))
+                };
+                let vname = variant_token.string;
+                let Some((_, variant)) = def.variant(vname) else {
+                    return Err(format!("enum `{name}` has no variant `{vname}`"))
+                };
+                if args.len() != variant.fields.len() {
+                    return Err(format!("variant `{name}.{vname}` has {n} field(s), but {m} were supplied",
+                                       n = variant.fields.len(), m = args.len()))
+                }
+                for (idx, (arg, (fty, _))) in args.iter().zip(variant.fields.iter()).enumerate() {
+                    arg.check_assign(fty, sym_map).map_err(|err| format!("field {idx} of `{name}.{vname}`: {err}"))?;
+                }
+                (Type::Enum(def.to_owned()), false)
+            }
+            Expr::Call(name_token, args) => match Self::check_builtin(name_token.string, args, sym_map)? {
+                Some(ret) => ret,
+                None => return Err(format!("`{name}` doesn't return a value", name = name_token.string))
+            }
+            Expr::UserCall(_, _, ty) => (ty.to_owned(), false),
+        };
+        Ok(ret)
+    }
+
+    /// Checks the arguments of a call to a builtin implemented by the runtime, returning the type of its result, if it has one.
+    /// Like `null`, the result of `alloc` is a flexible `u8*`, so it initializes any pointer.
+    /// The I/O builtins return what the syscall does, negative values being errors, and `-1` from `read_line` the end of the input.
+    /// `read_i64` and `read_f64` can't tell failure apart by their result, so they return `0` on it, and store a status:
+    /// `0` if a number was read, `-1` at the end of the input, and `-2` if the input isn't a number, or doesn't fit,
+    /// in which case the offending byte, or the whole run of digits, is dropped, so the next read moves on.
+    pub fn check_builtin(name: &str, args: &[Box::<Expr>], sym_map: &SymMap) -> Result::<Option::<(Type, bool)>, String> {
+        if name == "syscall" {
+            if args.is_empty() {
+                return Err("`syscall` needs the number of the syscall".to_owned())
+            }
+            if args.len() > 7 {
+                return Err(format!("`syscall` takes the number of the syscall and up to 6 arguments, but {n} were given", n = args.len()))
+            }
+            let (ty, _) = args[0].infer(sym_map)?;
+            if !ty.is_int() {
+                return Err(format!("number of the syscall must be an integer, but got: {ty}"))
+            }
+            // everything is passed in 64-bit registers, `str`s as the pointer to their data
+            for (idx, arg) in args.iter().enumerate().skip(1) {
+                let (ty, _) = arg.infer(sym_map)?;
+                if !ty.is_int() && !ty.is_ptr() && !matches!(ty, Type::Bool | Type::Char | Type::Str) {
+                    return Err(format!("argument {idx} of `syscall` must be an integer, a pointer or a str, but got: {ty}"))
+                }
+            }
+            return Ok(Some((Type::I64, false)))
+        }
+
+        use BuiltinParam::*;
+        let (params, ret): (&[BuiltinParam], _) = match name {
+            "alloc"     => (&[Int], Some((Type::Ptr(Box::new(Type::U8)), true))),
+            "free"      => (&[Ptr], None),
+            // the type of the result is the one of the pointer
+            "realloc"   => (&[Ptr, Int], None),
+            "open"      => (&[Str, Int], Some((Type::I64, false))),
+            "read"      => (&[Int, Ptr, Int], Some((Type::I64, false))),
+            "write"     => (&[Int, Buf, Int], Some((Type::I64, false))),
+            "close"     => (&[Int], Some((Type::I64, false))),
+            "read_line" => (&[Ptr, Int], Some((Type::I64, false))),
+            "read_i64"  => (&[Status], Some((Type::I64, false))),
+            "read_f64"  => (&[Status], Some((Type::F64, false))),
+            _ => return Err(format!("`{name}` can only be called as a statement"))
+        };
+        if args.len() != params.len() {
+            return Err(format!("`{name}` takes {n} argument{s}, but {m} {were} given",
+                               n = params.len(), s = if params.len() == 1 { "" } else { "s" }, m = args.len(),
+                               were = if args.len() == 1 { "was" } else { "were" }))
+        }
+
+        for (idx, (arg, param)) in args.iter().zip(params.iter()).enumerate() {
+            let (ty, _) = arg.infer(sym_map)?;
+            let (ok, what) = match param {
+                Int => (ty.is_int(), "an integer"),
+                Ptr => (ty.is_ptr(), "a pointer"),
+                Str => (ty == Type::Str, "a str"),
+                Buf => (ty.is_ptr() || ty == Type::Str, "a pointer or a str"),
+                Status => (ty == Type::Ptr(Box::new(Type::I64)) || matches!(**arg, Expr::Null), "an i64*"),
+            };
+            if !ok {
+                return Err(format!("argument {n} of `{name}` must be {what}, but got: {ty}", n = idx + 1))
+            }
+        }
+
+        if name == "realloc" {
+            return Ok(Some((args[0].infer(sym_map)?.0, false)))
+        }
+        Ok(ret)
+    }
+
+    /// Checks that a value of this expression can initialize a `ty` without an explicit cast, returning its type:
+    /// the conversion has to be lossless, unless it's an unsuffixed literal adapting to `ty`.
+    pub fn check_assign(&self, ty: &Type, sym_map: &SymMap) -> Result::<Type, String> {
+        // every element is checked on its own, so literals adapt to the element type
+        if let (Expr::Array(items), Type::Array(elem, len)) = (self, ty) {
+            if items.len() != *len {
+                return Err(format!("expected {len} elements, but the array literal has {n}", n = items.len()))
+            }
+            for item in items.iter() {
+                item.check_assign(elem, sym_map)?;
+            }
+            return Ok(ty.to_owned())
+        }
+        let (ety, flex) = self.infer(sym_map)?;
+        if Type::is_lossless(&ety, ty) || flex && Type::adapts(&ety, ty) { return Ok(ety) }
+        if ety.is_numeric() && ty.is_numeric() {
+            return Err(format!("implicit conversion from {ety} to {ty} may lose information, use `as {ty}` to convert explicitly"))
+        }
+        Err(format!("expected a value of type {ty}, but got: {ety}"))
+    }
+
+    /// Converts the value to `ty`, as if it initialized a `ty`, see [`Expr::check_assign`]:
+    /// allowed implicit conversions are made explicit, so the compiler lowers them like any other cast,
+    /// constants are folded right into `ty` instead, so they're still range checked.
+    pub fn coerce(self: Box::<Self>, ty: &Type, sym_map: &SymMap, loc: &Loc) -> Result::<Box::<Self>, String> {
+        let ety = self.check_assign(ty, sym_map)?;
+        let expr = if ety == *ty || self.is_const() { self } else { Box::new(Expr::Cast(self, ty.to_owned())) };
+        Ok(expr.fold(ty, sym_map, loc))
+    }
+
+    /// Constant divisors are known at parse time, so dividing by a zero one is reported rather than folded.
+    fn check_divisor(rhs: &Self, ty: &Type, sym_map: &SymMap) -> Result::<(), String> {
+        if rhs.is_const() && (ty.is_float() && rhs.eval_flt(sym_map) == 0.0 || ty.is_int() && rhs.eval_int(sym_map) == 0) {
+            return Err("division by zero".to_owned())
+        }
+        Ok(())
+    }
+
+    /// Type both operands of a binary operator are converted to, see [`Type::promote`].
+    /// Unsuffixed literals take the type of the other operand, as long as they fit into it.
+    pub fn unify(lhs: &Self, rhs: &Self, sym_map: &SymMap) -> Result::<(Type, bool), String> {
+        let (lty, lflex) = lhs.infer(sym_map)?;
+        let (rty, rflex) = rhs.infer(sym_map)?;
+
+        let adapt = |lit: &Self, ty: &Type| {
+            if ty.is_int() && lit.is_const() {
+                let v = lit.eval_int(sym_map);
+                if !ty.fits(v) {
+                    return Err(format!("literal out of range for {ty}: {v}"))
+                }
+            }
+            Ok((ty.to_owned(), false))
+        };
+
+        match (lflex, rflex) {
+            (true, false) if Type::adapts(&lty, &rty) => adapt(lhs, &rty),
+            (false, true) if Type::adapts(&rty, &lty) => adapt(rhs, &lty),
+            (true, true) if lty.is_int() && rty.is_float() => Ok((rty, true)),
+            (true, true) if lty.is_float() && rty.is_int() => Ok((lty, true)),
+            _ => Type::promote(&lty, &rty)
+                .map(|ty| (ty, lflex && rflex))
+                .ok_or_else(|| match (&lty, &rty) {
+                    (int, flt) | (flt, int) if int.is_int() && flt.is_float() => format!(
+                        "implicit conversion from {int} to {flt} may lose information, use `as {flt}` to convert explicitly"
+                    ),
+                    _ => format!("mismatched operand types: {lty} and {rty}")
+                })
+        }
+    }
+
+    /// Folds constant expressions into a literal of type `ty`, anything else is left for the compiler.
+    pub fn fold(self: Box::<Self>, ty: &Type, sym_map: &SymMap, loc: &Loc) -> Box::<Self> {
+        if !self.is_const() { return self }
+        match ty {
+            _ if ty.is_int() => {
+                let v = self.eval_int(sym_map);
+                if !ty.fits(v) {
+                    panic!("{loc} error: constant out of range for {ty}: {v}")
+                }
+                Box::new(Expr::Int(ty.wrap(v), ty.to_owned()))
+            }
+            Type::F32  => Box::new(Expr::Flt(self.eval_flt(sym_map) as f32 as _, Type::F32)),
+            Type::F64  => Box::new(Expr::Flt(self.eval_flt(sym_map), Type::F64)),
+            Type::Bool => Box::new(Expr::Bool(self.eval_bool(sym_map))),
+            Type::Char => Box::new(Expr::Char(self.eval_int(sym_map) as _)),
+            Type::Array(elem, _) => match *self {
+                Expr::Array(items) => Box::new(Expr::Array(items.into_iter().map(|item| item.fold(elem, sym_map, loc)).collect())),
+                expr => Box::new(expr)
+            }
+            Type::Struct(def) => match *self {
+                Expr::Struct(name_token, fields) => Box::new(Expr::Struct(name_token, fields.into_iter().map(|(field_token, value)| {
+                    let ref fty = def.field(field_token.string).unwrap().ty;
+                    let value = value.fold(fty, sym_map, loc);
+                    (field_token, value)
+                }).collect())),
+                expr => Box::new(expr)
+            }
+            Type::Enum(def) => match *self {
+                Expr::Variant(name_token, variant_token, args) => {
+                    let (_, variant) = def.variant(variant_token.string).unwrap();
+                    let args = args.into_iter().zip(variant.fields.iter()).map(|(arg, (fty, _))| {
+                        arg.fold(fty, sym_map, loc)
+                    }).collect();
+                    Box::new(Expr::Variant(name_token, variant_token, args))
+                }
+                expr => Box::new(expr)
+            }
+            _ => self,
+        }
+    }
+
+    #[inline]
+    fn int_type(&self, sym_map: &SymMap) -> Type {
+        self.infer(sym_map).map_or(Type::I64, |(ty, _)| ty)
+    }
+
+    /// Evaluates in `i128`, so every `u64` and `i64` fits, and overflow is caught when folding.
+    pub fn eval_int(&self, sym_map: &SymMap) -> i128 {
         match self {
             Expr::I64(ival) => *ival,
-            Expr::F64(fval) => *fval as _,
-            Expr::Lit(..) => todo!(),
+            Expr::Int(ival, Type::U64) => *ival as u64 as _,
+            Expr::Int(ival, _) => *ival as _,
+            Expr::F64(fval) | Expr::Flt(fval, _) => *fval as _,
+            Expr::Str(..) => panic!("error: string can't be used as an integer"),
+            Expr::Bool(bval) => *bval as _,
+            Expr::Char(c) => *c as _,
+            Expr::Null => 0,
+            Expr::Lit(..) | Expr::Index(..) | Expr::Array(..) | Expr::AddrOf(..) | Expr::Deref(..) |
+            Expr::Struct(..) | Expr::Field(..) | Expr::Variant(..) | Expr::Call(..) | Expr::UserCall(..) => todo!(),
             Expr::Add(ref lhs, ref rhs) => lhs.eval_int(sym_map) + rhs.eval_int(sym_map),
             Expr::Sub(ref lhs, ref rhs) => lhs.eval_int(sym_map) - rhs.eval_int(sym_map),
-            Expr::Mul(ref lhs, ref rhs) => lhs.eval_int(sym_map) * rhs.eval_int(sym_map),
-            Expr::Div(ref lhs, ref rhs) => {
-                let rval = rhs.eval_int(sym_map);
-                if rval == 0 { 0 } else { lhs.eval_int(sym_map) / rval }
+            Expr::Mul(ref lhs, ref rhs) => lhs.eval_int(sym_map).wrapping_mul(rhs.eval_int(sym_map)),
+            // zero divisors are rejected by `infer` already
+            Expr::Div(ref lhs, ref rhs) => lhs.eval_int(sym_map) / rhs.eval_int(sym_map),
+            Expr::Rem(ref lhs, ref rhs) => lhs.eval_int(sym_map) % rhs.eval_int(sym_map),
+            Expr::Neg(ref expr) => -expr.eval_int(sym_map),
+            Expr::BitAnd(ref lhs, ref rhs) => lhs.eval_int(sym_map) & rhs.eval_int(sym_map),
+            Expr::BitOr(ref lhs, ref rhs)  => lhs.eval_int(sym_map) | rhs.eval_int(sym_map),
+            Expr::BitXor(ref lhs, ref rhs) => lhs.eval_int(sym_map) ^ rhs.eval_int(sym_map),
+            // the shift amount is taken modulo the width of the register, just like at runtime
+            Expr::Shl(ref lhs, ref rhs) | Expr::Shr(ref lhs, ref rhs) => {
+                let bits = if self.int_type(sym_map).size() == 8 { 64 } else { 32 };
+                let amount = rhs.eval_int(sym_map) as u32 % bits;
+                // unsigned values are never negative here, so `>>` is a logical shift for them
+                if let Expr::Shl(..) = self {
+                    lhs.eval_int(sym_map) << amount
+                } else {
+                    lhs.eval_int(sym_map) >> amount
+                }
+            }
+            Expr::BitNot(ref expr) => {
+                let (min, max) = self.int_type(sym_map).int_range();
+                min + max - expr.eval_int(sym_map)
             }
+            // the only place where a float is truncated, or an integer wraps around
+            Expr::Cast(ref expr, ref ty) => match expr.infer(sym_map) {
+                Ok((from, _)) if from.is_float() => ty.wrap(expr.eval_flt(sym_map) as _) as _,
+                Ok((Type::U64, _)) if *ty != Type::U64 => ty.wrap(expr.eval_int(sym_map)) as _,
+                _ if *ty == Type::U64 => ty.wrap(expr.eval_int(sym_map)) as u64 as _,
+                _ => ty.wrap(expr.eval_int(sym_map)) as _,
+            }
+            _ => self.eval_bool(sym_map) as _
         }
     }
 
     pub fn eval_flt(&self, sym_map: &SymMap) -> f64 {
         match *self {
             Expr::I64(ival) => ival as _,
-            Expr::F64(fval) => fval,
-            Expr::Lit(..) => todo!(),
+            Expr::Int(..) => self.eval_int(sym_map) as _,
+            Expr::F64(fval) | Expr::Flt(fval, _) => fval,
+            Expr::Str(..) => panic!("error: string can't be used as a float"),
+            Expr::Bool(bval) => bval as u8 as _,
+            Expr::Lit(..) | Expr::Index(..) | Expr::Array(..) | Expr::AddrOf(..) | Expr::Deref(..) |
+            Expr::Struct(..) | Expr::Field(..) | Expr::Variant(..) | Expr::Call(..) | Expr::UserCall(..) => todo!(),
             Expr::Add(ref lhs, ref rhs) => lhs.eval_flt(sym_map) + rhs.eval_flt(sym_map),
             Expr::Sub(ref lhs, ref rhs) => lhs.eval_flt(sym_map) - rhs.eval_flt(sym_map),
             Expr::Mul(ref lhs, ref rhs) => lhs.eval_flt(sym_map) * rhs.eval_flt(sym_map),
-            Expr::Div(ref lhs, ref rhs) => {
-                let rval = rhs.eval_flt(sym_map);
-                if rval == 0.0 { 0.0 } else { lhs.eval_flt(sym_map) / rval }
+            Expr::Div(ref lhs, ref rhs) => lhs.eval_flt(sym_map) / rhs.eval_flt(sym_map),
+            Expr::Neg(ref expr) => -expr.eval_flt(sym_map),
+            // integer-only operations on unsuffixed literals, that adapted to a float
+            Expr::Rem(..) | Expr::BitAnd(..) | Expr::BitOr(..) | Expr::BitXor(..) |
+            Expr::Shl(..) | Expr::Shr(..) | Expr::BitNot(..) => self.eval_int(sym_map) as _,
+            Expr::Cast(ref expr, ref ty) => {
+                let fval = match expr.infer(sym_map) {
+                    Ok((from, _)) if from.is_float() => expr.eval_flt(sym_map),
+                    _ => expr.eval_int(sym_map) as _,
+                };
+                if *ty == Type::F32 { fval as f32 as _ } else { fval }
             }
+            _ => self.eval_bool(sym_map) as u8 as _
+        }
+    }
+
+    pub fn eval_bool(&self, sym_map: &SymMap) -> bool {
+        macro_rules! cmp {
+            ($lhs: expr, $rhs: expr, $op: tt) => {
+                if Self::unify($lhs, $rhs, sym_map).map_or(false, |(ty, _)| ty.is_float()) {
+                    $lhs.eval_flt(sym_map) $op $rhs.eval_flt(sym_map)
+                } else {
+                    $lhs.eval_int(sym_map) $op $rhs.eval_int(sym_map)
+                }
+            };
+        }
+
+        match self {
+            Expr::Bool(bval) => *bval,
+            Expr::Eq(lhs, rhs) => cmp!(lhs, rhs, ==),
+            Expr::Ne(lhs, rhs) => cmp!(lhs, rhs, !=),
+            Expr::Lt(lhs, rhs) => cmp!(lhs, rhs, <),
+            Expr::Le(lhs, rhs) => cmp!(lhs, rhs, <=),
+            Expr::Gt(lhs, rhs) => cmp!(lhs, rhs, >),
+            Expr::Ge(lhs, rhs) => cmp!(lhs, rhs, >=),
+            Expr::And(lhs, rhs) => lhs.eval_bool(sym_map) && rhs.eval_bool(sym_map),
+            Expr::Or(lhs, rhs)  => lhs.eval_bool(sym_map) || rhs.eval_bool(sym_map),
+            Expr::Not(expr) => !expr.eval_bool(sym_map),
+            _ => self.eval_int(sym_map) != 0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr_parser::tests::with_expr;
+
+    fn eval_int(src: &str) -> i128 {
+        with_expr(src, &[], |expr, sym_map| expr.eval_int(sym_map))
+    }
+
+    fn eval_flt(src: &str) -> f64 {
+        with_expr(src, &[], |expr, sym_map| expr.eval_flt(sym_map))
+    }
+
+    fn check_assign(src: &str, vars: &[(&'static str, Type)], ty: Type) -> Result::<Type, String> {
+        with_expr(src, vars, |expr, sym_map| expr.check_assign(&ty, sym_map))
+    }
+
+    #[test]
+    fn casts_between_integers_wrap() {
+        assert_eq!(eval_int("300 as u8"), 44);
+        assert_eq!(eval_int("-1 as u64"), u64::MAX as i128);
+        assert_eq!(eval_int("255 as u8 as i8"), -1);
+        assert_eq!(eval_int("65536 as i16"), 0);
+        assert_eq!(eval_int("-1 as u32 as i64"), u32::MAX as i128);
+    }
+
+    #[test]
+    fn casts_from_floats_truncate_toward_zero() {
+        assert_eq!(eval_int("3.9 as i64"), 3);
+        assert_eq!(eval_int("-3.9 as i64"), -3);
+        assert_eq!(eval_int("257.5 as u8"), 1);
+    }
+
+    #[test]
+    fn casts_to_floats() {
+        assert_eq!(eval_flt("7 as f64"), 7.0);
+        assert_eq!(eval_flt("0.1 as f32"), 0.1_f32 as f64);
+        assert_eq!(eval_flt("(1 as f32) / 4.0"), 0.25);
+    }
+
+    #[test]
+    fn casts_from_bools_and_chars() {
+        assert_eq!(eval_int("true as i32"), 1);
+        assert_eq!(eval_int("'a' as u8"), 97);
+        assert_eq!(eval_int("98 as char as i64"), 98);
+    }
+
+    #[test]
+    fn castable_types() {
+        assert!(Type::is_castable(&Type::F64, &Type::U8));
+        assert!(Type::is_castable(&Type::Bool, &Type::I64));
+        assert!(Type::is_castable(&Type::Ptr(Box::new(Type::U8)), &Type::U64));
+        assert!(!Type::is_castable(&Type::Ptr(Box::new(Type::U8)), &Type::I32));
+        assert!(!Type::is_castable(&Type::Bool, &Type::F64));
+        assert!(!Type::is_castable(&Type::Str, &Type::I64));
+    }
+
+    #[test]
+    fn lossless_conversions_are_implicit() {
+        assert_eq!(check_assign("x", &[("x", Type::I32)], Type::I64), Ok(Type::I32));
+        assert_eq!(check_assign("x", &[("x", Type::U32)], Type::I64), Ok(Type::U32));
+        assert_eq!(check_assign("x", &[("x", Type::I16)], Type::F32), Ok(Type::I16));
+        assert_eq!(check_assign("x", &[("x", Type::I32)], Type::F64), Ok(Type::I32));
+        assert_eq!(check_assign("x", &[("x", Type::F32)], Type::F64), Ok(Type::F32));
+    }
+
+    #[test]
+    fn lossy_conversions_need_a_cast() {
+        let lossy = [
+            (Type::I64, Type::I32), (Type::U64, Type::I64), (Type::I8, Type::U64),
+            (Type::I64, Type::F64), (Type::I32, Type::F32), (Type::F64, Type::F32), (Type::F64, Type::I64),
+        ];
+        for (from, to) in lossy {
+            let err = check_assign("x", &[("x", from.to_owned())], to.to_owned()).unwrap_err();
+            assert!(err.contains("may lose information"), "{from} to {to}: {err}");
+            assert_eq!(check_assign(&format!("x as {to}"), &[("x", from.to_owned())], to.to_owned()), Ok(to));
         }
     }
+
+    #[test]
+    fn arithmetic_only_mixes_ints_and_floats_losslessly() {
+        let unify = |src, vars| with_expr(src, vars, |expr, sym_map| match expr {
+            Expr::Add(lhs, rhs) | Expr::Mul(lhs, rhs) => Expr::unify(lhs, rhs, sym_map),
+            _ => unreachable!()
+        });
+        let err = unify("a + 1.5", &[("a", Type::I64)]).unwrap_err();
+        assert!(err.contains("from i64 to f64 may lose information, use `as f64`"), "{err}");
+        let err = unify("a * 1.5f32", &[("a", Type::I32)]).unwrap_err();
+        assert!(err.contains("from i32 to f32 may lose information, use `as f32`"), "{err}");
+        assert_eq!(unify("a as f64 + 1.5", &[("a", Type::I64)]), Ok((Type::F64, false)));
+        assert_eq!(unify("a + 1.5", &[("a", Type::I32)]), Ok((Type::F64, false)));
+        assert_eq!(unify("a * 1.5f32", &[("a", Type::I16)]), Ok((Type::F32, false)));
+        assert_eq!(unify("1 + 1.5", &[]), Ok((Type::F64, true)));
+    }
+
+    #[test]
+    fn struct_fields_are_aligned_naturally() {
+        let def = StructDef::new("S".to_owned(), vec![
+            ("a".to_owned(), Type::U8), ("b".to_owned(), Type::I64), ("c".to_owned(), Type::U16),
+        ]);
+        let offsets = def.fields.iter().map(|field| field.offset).collect::<Vec::<_>>();
+        assert_eq!(offsets, [0, 8, 16]);
+        assert_eq!((def.size, def.align), (24, 8));
+
+        let small = StructDef::new("T".to_owned(), vec![("a".to_owned(), Type::U8), ("b".to_owned(), Type::U16)]);
+        assert_eq!(small.field("b").map(|field| field.offset), Some(2));
+        assert_eq!((small.size, small.align), (4, 2));
+    }
+
+    #[test]
+    fn nested_structs_and_arrays_keep_the_alignment_of_their_elements() {
+        let inner = Type::Struct(Rc::new(StructDef::new("I".to_owned(), vec![("x".to_owned(), Type::F32), ("y".to_owned(), Type::U8)])));
+        let def = StructDef::new("O".to_owned(), vec![
+            ("tag".to_owned(), Type::U8), ("inner".to_owned(), inner), ("bytes".to_owned(), Type::Array(Box::new(Type::U8), 3)),
+        ]);
+        let offsets = def.fields.iter().map(|field| field.offset).collect::<Vec::<_>>();
+        assert_eq!(offsets, [0, 4, 12]);
+        assert_eq!((def.size, def.align), (16, 4));
+    }
+
+    #[test]
+    fn payloads_follow_the_tag() {
+        let def = EnumDef::new("Shape".to_owned(), vec![
+            ("Circle".to_owned(), vec![Type::F64]),
+            ("Rect".to_owned(), vec![Type::F64, Type::F64]),
+            ("Small".to_owned(), vec![Type::U8, Type::I32]),
+            ("Empty".to_owned(), vec![]),
+        ]);
+        let offsets = |name| def.variant(name).unwrap().1.fields.iter().map(|(_, offset)| *offset).collect::<Vec::<_>>();
+        assert_eq!(offsets("Circle"), [8]);
+        assert_eq!(offsets("Rect"), [8, 16]);
+        assert_eq!(offsets("Small"), [8, 12]);
+        assert_eq!(offsets("Empty"), []);
+        assert_eq!(def.variant("Empty").map(|(tag, _)| tag), Some(3));
+        assert_eq!((def.size, def.align), (24, 8));
+    }
+
+    #[test]
+    fn unsuffixed_literals_adapt_to_the_type_they_initialize() {
+        assert!(check_assign("200", &[], Type::U8).is_ok());
+        assert!(check_assign("1", &[], Type::F32).is_ok());
+        assert!(check_assign("1.5", &[], Type::I64).is_err());
+        assert!(check_assign("1i64", &[], Type::I32).is_err());
+    }
 }
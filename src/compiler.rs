@@ -1,8 +1,8 @@
-use crate::parser::Ctx;
-use crate::lexer::Token;
+use crate::parser::{Ctx, SymKind, BUILTINS, ENTRY};
+use crate::lexer::{Loc, Token};
 use crate::ast::{
-    Ast, Type, Fn,
-    Asts, AstKind, VarDecl, FnCall, Expr
+    Ast, Type, Fn, StructDef, EnumDef,
+    Asts, AstKind, VarDecl, Assign, FnCall, Expr, Match
 };
 
 use std::{
@@ -13,6 +13,15 @@ use std::{
 
 const TAB: &'static str = "\t";
 
+const STDOUT: u8 = 1;
+const STDERR: u8 = 2;
+
+/// Amount of fractional digits `f64`s are printed with, unless the format string says otherwise.
+const DEFAULT_PRECISION: usize = 10;
+
+/// Most fractional digits the runtime prints, 10^18 being the biggest power of ten that fits into an `i64`.
+const MAX_PRECISION: usize = 18;
+
 macro_rules! writetln {
     ($dst: expr, $($arg: tt)*) => {{
         write!($dst, "{TAB}")?;
@@ -27,63 +36,831 @@ macro_rules! writet {
     }};
 }
 
+enum FmtPiece {
+    Str(Vec::<u8>),
+    Arg { precision: Option::<usize> },
+}
+
+impl FmtPiece {
+    /// Splits a format string into literal pieces and `{}` / `{:.N}` placeholders, `{{` and `}}` being escapes.
+    fn parse(fmt: &[u8], loc: &Loc) -> Vec::<Self> {
+        let mut ret = Vec::new();
+        let mut curr = Vec::new();
+        let mut i = 0;
+        while i < fmt.len() {
+            match (fmt[i], fmt.get(i + 1)) {
+                (b'{', Some(b'{')) | (b'}', Some(b'}')) => {
+                    curr.push(fmt[i]);
+                    i += 2;
+                }
+                (b'{', _) => {
+                    let Some(len) = fmt[i..].iter().position(|b| *b == b'}') else {
+                        panic!("{loc} error: unmatched `{{` in format string")
+                    };
+
+                    let spec = &fmt[i + 1..i + len];
+                    let precision = match spec {
+                        [] => None,
+                        [b':', b'.', digits @ ..] if !digits.is_empty() && digits.iter().all(u8::is_ascii_digit) => {
+                            let digits = unsafe { std::str::from_utf8_unchecked(digits) };
+                            match digits.parse() {
+                                Ok(precision) if precision <= MAX_PRECISION => Some(precision),
+                                _ => panic!("{loc} error: precision in format string is too large: {digits}, at most {MAX_PRECISION} digits are supported")
+                            }
+                        }
+                        _ => panic!("{loc} error: unsupported format spec: `{{{spec}}}`, expected `{{}}` or `{{:.N}}`",
+                                    spec = String::from_utf8_lossy(spec))
+                    };
+
+                    if !curr.is_empty() {
+                        ret.push(Self::Str(std::mem::take(&mut curr)));
+                    }
+                    ret.push(Self::Arg { precision });
+                    i += len + 1;
+                }
+                (b'}', _) => panic!("{loc} error: unmatched `}}` in format string"),
+                (b, _) => {
+                    curr.push(b);
+                    i += 1;
+                }
+            }
+        }
+
+        if !curr.is_empty() {
+            ret.push(Self::Str(curr));
+        }
+
+        ret
+    }
+}
+
 pub struct Compiler {
     s: File,
-    gen_file_path: String,
+    strs: Vec::<Vec::<u8>>,
+    tmps: usize,
+    lbls: usize,
+    bounds_checks: bool,
 }
 
 impl Compiler {
-    pub fn new(_file_path: &str) -> std::io::Result::<Self> {
-        let gen_file_path = "out.ssa".to_owned();
-        let s = File::create(&gen_file_path)?;
-        let compiler = Self { s, gen_file_path };
+    pub fn new(_file_path: &str, bounds_checks: bool) -> std::io::Result::<Self> {
+        let s = File::create("out.ssa")?;
+        let compiler = Self { s, strs: Vec::new(), tmps: 0, lbls: 0, bounds_checks };
         Ok(compiler)
     }
 
-    fn compile_var_decl(&mut self, vd: &VarDecl, ctx: &Box::<Ctx>) -> std::io::Result::<()> {
-        writet!(self.s, "%{name} =", name = vd.name_token.string)?;
-        fn compile_expr(s: &mut File, ctx: &Box::<Ctx>, expr: &Box::<Expr>) -> std::io::Result::<()> {
-            match expr.deref() {
-                Expr::Lit(lit) => if let Some(sym) = ctx.sym_map().get(lit.string) {
-                    match sym.kind {
-                        AstKind::VarDecl(ref vd) => compile_expr(s, ctx, &vd.value)?,
-                        _ => todo!()
-                    }
+    /// Registers a string literal to be emitted as `$strN` data, returning `N`.
+    #[inline]
+    fn append_str(&mut self, bytes: &[u8]) -> usize {
+        self.strs.push(bytes.to_owned());
+        self.strs.len() - 1
+    }
+
+    fn compile_strs(&mut self) -> std::io::Result::<()> {
+        for (id, bytes) in self.strs.iter().enumerate() {
+            write!(self.s, "data $str{id} = {{ ")?;
+            // printable runs go in as `b "..."`, everything else byte by byte, so we never depend on escapes in the IL
+            for run in bytes.chunk_by(|a, b| Self::is_data_printable(*a) == Self::is_data_printable(*b)) {
+                if Self::is_data_printable(run[0]) {
+                    write!(self.s, "b \"{run}\", ", run = unsafe { std::str::from_utf8_unchecked(run) })?;
                 } else {
-                    panic!("{loc} error: undefined symbol: {string}",
-                           loc = lit.loc, string = lit.string)
+                    for b in run { write!(self.s, "b {b}, ")?; }
                 }
-                Expr::I64(int) => writeln!(s, "l copy {int}")?,
-                Expr::F64(flt) => writeln!(s, "d copy {bits}", bits = flt.to_bits())?,
-                _ => todo!()
+            }
+            writeln!(self.s, "b 0 }}")?;
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn is_data_printable(b: u8) -> bool {
+        matches!(b, b' '..=b'~') && b != b'"' && b != b'\\'
+    }
+
+    #[inline]
+    fn tmp(&mut self) -> String {
+        self.tmps += 1;
+        format!("%.{n}", n = self.tmps)
+    }
+
+    #[inline]
+    fn lbl(&mut self) -> usize {
+        self.lbls += 1;
+        self.lbls
+    }
+
+    /// Lowers `expr` into the current function, returning the operand holding its value and its type.
+    /// `str`s are pairs: the returned operand is the pointer, and `{operand}.len` is the length.
+    fn compile_expr(&mut self, expr: &Box::<Expr>, ctx: &Box::<Ctx>) -> std::io::Result::<(String, Type)> {
+        let ret = match expr.deref() {
+            Expr::I64(int)   => {
+                let ty = Type::of_int_lit(*int);
+                (ty.wrap(*int).to_string(), ty)
+            }
+            Expr::Int(int, ty) => (int.to_string(), ty.to_owned()),
+            Expr::F64(flt)   => (flt.to_bits().to_string(), Type::F64),
+            Expr::Flt(flt, Type::F32) => ((*flt as f32).to_bits().to_string(), Type::F32),
+            Expr::Flt(flt, ty) => (flt.to_bits().to_string(), ty.to_owned()),
+            Expr::Bool(bool) => ((*bool as u8).to_string(), Type::Bool),
+            Expr::Char(c) => (c.to_string(), Type::Char),
+            Expr::Str(bytes) => {
+                let id = self.append_str(bytes);
+                let t = self.tmp();
+                writetln!(self.s, "{t} =l copy $str{id}")?;
+                writetln!(self.s, "{t}.len =l copy {len}", len = bytes.len())?;
+                (t, Type::Str)
+            }
+            Expr::Lit(lit) => match ctx.sym_map().get(lit.string) {
+                Some(sym) => match &sym.kind {
+                    SymKind::Local => (format!("%{name}", name = sym.il_name), sym.ty.to_owned()),
+                    SymKind::Stack => (self.load(&format!("%{name}.addr", name = sym.il_name), &sym.ty)?, sym.ty.to_owned()),
+                    SymKind::Global => (self.load_global(&sym.il_name, &sym.ty)?, sym.ty.to_owned()),
+                    SymKind::Const(value) => self.compile_expr(value, ctx)?,
+                    // rejected by the parser
+                    SymKind::Type => unreachable!()
+                }
+                None => panic!("{loc} error: undefined symbol: {string}", loc = lit.loc, string = lit.string)
+            }
+            Expr::Add(lhs, rhs) | Expr::Sub(lhs, rhs) if [lhs, rhs].iter().any(|e| {
+                e.infer(ctx.sym_map()).is_ok_and(|(ty, _)| ty.is_ptr())
+            }) => self.compile_ptr_arith(expr, lhs, rhs, ctx)?,
+            Expr::Add(lhs, rhs) | Expr::Sub(lhs, rhs) |
+            Expr::Mul(lhs, rhs) | Expr::Div(lhs, rhs) |
+            Expr::Rem(lhs, rhs) | Expr::BitAnd(lhs, rhs) |
+            Expr::BitOr(lhs, rhs) | Expr::BitXor(lhs, rhs) |
+            Expr::Shl(lhs, rhs) | Expr::Shr(lhs, rhs) => {
+                let (ty, _) = Expr::unify(lhs, rhs, ctx.sym_map()).unwrap();
+                let (l, lty) = self.compile_expr(lhs, ctx)?;
+                let (r, rty) = self.compile_expr(rhs, ctx)?;
+                let l = self.convert(l, &lty, &ty)?;
+                let r = self.convert(r, &rty, &ty)?;
+                let unsigned = ty.is_int() && !ty.is_signed();
+                let inst = match expr.deref() {
+                    Expr::Add(..)    => "add",
+                    Expr::Sub(..)    => "sub",
+                    Expr::Mul(..)    => "mul",
+                    Expr::Div(..)    => if unsigned { "udiv" } else { "div" },
+                    Expr::Rem(..)    => if unsigned { "urem" } else { "rem" },
+                    Expr::BitAnd(..) => "and",
+                    Expr::BitOr(..)  => "or",
+                    Expr::BitXor(..) => "xor",
+                    Expr::Shl(..)    => "shl",
+                    _                => if unsigned { "shr" } else { "sar" },
+                };
+                let t = self.tmp();
+                writetln!(self.s, "{t} ={il} {inst} {l}, {r}", il = ty.to_il_str())?;
+                (self.normalize(t, &ty)?, ty)
+            }
+            Expr::Eq(lhs, rhs) | Expr::Ne(lhs, rhs) |
+            Expr::Lt(lhs, rhs) | Expr::Le(lhs, rhs) |
+            Expr::Gt(lhs, rhs) | Expr::Ge(lhs, rhs) => {
+                let (ty, _) = Expr::unify(lhs, rhs, ctx.sym_map()).unwrap();
+                let (l, lty) = self.compile_expr(lhs, ctx)?;
+                let (r, rty) = self.compile_expr(rhs, ctx)?;
+                let l = self.convert(l, &lty, &ty)?;
+                let r = self.convert(r, &rty, &ty)?;
+                // floats use the ordered comparisons, ints are compared according to their signedness
+                let sign = if ty.is_float() { "" } else if ty.is_signed() { "s" } else { "u" };
+                let cmp = match expr.deref() {
+                    Expr::Eq(..) => "eq".to_owned(),
+                    Expr::Ne(..) => "ne".to_owned(),
+                    Expr::Lt(..) => format!("{sign}lt"),
+                    Expr::Le(..) => format!("{sign}le"),
+                    Expr::Gt(..) => format!("{sign}gt"),
+                    _            => format!("{sign}ge"),
+                };
+                let t = self.tmp();
+                writetln!(self.s, "{t} =w c{cmp}{ty} {l}, {r}", ty = ty.to_il_str())?;
+                (t, Type::Bool)
+            }
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                let (name, is_and) = if let Expr::And(..) = expr.deref() { ("and", true) } else { ("or", false) };
+                let n = self.lbl();
+                let t = self.tmp();
+                let (l, _) = self.compile_expr(lhs, ctx)?;
+                writetln!(self.s, "{t} =w copy {l}")?;
+                // the rhs is only evaluated when the lhs didn't decide the result already
+                if is_and {
+                    writetln!(self.s, "jnz {l}, @{name}.rhs.{n}, @{name}.end.{n}")?;
+                } else {
+                    writetln!(self.s, "jnz {l}, @{name}.end.{n}, @{name}.rhs.{n}")?;
+                }
+                writeln!(self.s, "@{name}.rhs.{n}")?;
+                let (r, _) = self.compile_expr(rhs, ctx)?;
+                writetln!(self.s, "{t} =w copy {r}")?;
+                writeln!(self.s, "@{name}.end.{n}")?;
+                (t, Type::Bool)
+            }
+            Expr::Not(expr) => {
+                let (op, _) = self.compile_expr(expr, ctx)?;
+                let t = self.tmp();
+                writetln!(self.s, "{t} =w ceqw {op}, 0")?;
+                (t, Type::Bool)
+            }
+            Expr::Neg(expr) => {
+                let (op, ty) = self.compile_expr(expr, ctx)?;
+                let t = self.tmp();
+                writetln!(self.s, "{t} ={il} neg {op}", il = ty.to_il_str())?;
+                (self.normalize(t, &ty)?, ty)
+            }
+            Expr::BitNot(expr) => {
+                let (op, ty) = self.compile_expr(expr, ctx)?;
+                let t = self.tmp();
+                writetln!(self.s, "{t} ={il} xor {op}, -1", il = ty.to_il_str())?;
+                (self.normalize(t, &ty)?, ty)
+            }
+            Expr::Array(items) => {
+                let (ty, _) = expr.infer(ctx.sym_map()).unwrap();
+                let Type::Array(ref elem, _) = ty else { unreachable!() };
+                (self.compile_array(items, elem, ctx)?, ty)
+            }
+            Expr::Index(base, index, loc) => {
+                let (addr, elem) = self.compile_elem_addr(base, index, loc, ctx)?;
+                (self.load(&addr, &elem)?, elem)
+            }
+            Expr::Null => ("0".to_owned(), Type::Ptr(Box::new(Type::U8))),
+            Expr::AddrOf(expr) => {
+                let (addr, ty) = self.compile_place_addr(expr, ctx)?;
+                (addr, Type::Ptr(Box::new(ty)))
+            }
+            Expr::Deref(expr) => {
+                let (p, ty) = self.compile_expr(expr, ctx)?;
+                let Type::Ptr(ty) = ty else { unreachable!() };
+                (self.load(&p, &ty)?, *ty)
+            }
+            Expr::Struct(..) | Expr::Variant(..) => {
+                let (ty, _) = expr.infer(ctx.sym_map()).unwrap();
+                (self.compile_value(expr, &ty, ctx)?, ty)
+            }
+            Expr::Field(..) => {
+                let (addr, ty) = self.compile_place_addr(expr, ctx)?;
+                (self.load(&addr, &ty)?, ty)
+            }
+            Expr::Call(name_token, args) => self.compile_builtin(name_token.string, args, ctx)?.unwrap(),
+            Expr::UserCall(name_token, args, ty) => (self.compile_call(name_token.string, args, Some(ty), ctx)?.unwrap(), ty.to_owned()),
+            Expr::Cast(expr, ty) => {
+                let (op, from) = self.compile_expr(expr, ctx)?;
+                // bools are already zero or one, and chars are bytes, so they convert like `u8`s
+                let as_byte = |ty: &Type| if matches!(ty, Type::Bool | Type::Char) { Type::U8 } else { ty.to_owned() };
+                (self.convert(op, &as_byte(&from), &as_byte(ty))?, ty.to_owned())
+            }
+        };
+        Ok(ret)
+    }
+
+    /// Address of the memory `expr` refers to, the parser has made sure it's a place.
+    fn compile_place_addr(&mut self, expr: &Box::<Expr>, ctx: &Box::<Ctx>) -> std::io::Result::<(String, Type)> {
+        let ret = match expr.deref() {
+            Expr::Lit(lit) => {
+                let sym = &ctx.sym_map()[lit.string];
+                let addr = match sym.kind {
+                    SymKind::Stack => format!("%{name}.addr", name = sym.il_name),
+                    SymKind::Global => format!("${name}", name = sym.il_name),
+                    // aggregates are addresses already
+                    SymKind::Local if sym.ty.is_aggregate() => format!("%{name}", name = sym.il_name),
+                    // every local, whose address is taken, has been put into a stack slot by the parser
+                    _ => unreachable!()
+                };
+                (addr, sym.ty.to_owned())
+            }
+            Expr::Index(base, index, loc) => self.compile_elem_addr(base, index, loc, ctx)?,
+            Expr::Field(base, field_token) => {
+                let (base, ty) = self.compile_expr(base, ctx)?;
+                let Type::Struct(def) = ty else { unreachable!() };
+                let field = def.field(field_token.string).unwrap();
+                let addr = self.tmp();
+                writetln!(self.s, "{addr} =l add {base}, {off}", off = field.offset)?;
+                (addr, field.ty.to_owned())
+            }
+            Expr::Deref(expr) => {
+                let (p, ty) = self.compile_expr(expr, ctx)?;
+                let Type::Ptr(ty) = ty else { unreachable!() };
+                (p, *ty)
+            }
+            _ => unreachable!()
+        };
+        Ok(ret)
+    }
+
+    /// `p + i`, `i + p` and `p - i` move the pointer by `i` elements, `p - q` is the distance between two pointers in elements.
+    fn compile_ptr_arith(&mut self, expr: &Expr, lhs: &Box::<Expr>, rhs: &Box::<Expr>, ctx: &Box::<Ctx>) -> std::io::Result::<(String, Type)> {
+        let (l, lty) = self.compile_expr(lhs, ctx)?;
+        let (r, rty) = self.compile_expr(rhs, ctx)?;
+        if let (Type::Ptr(elem), Type::Ptr(..)) = (&lty, &rty) {
+            let d = self.tmp();
+            writetln!(self.s, "{d} =l sub {l}, {r}")?;
+            let t = self.tmp();
+            writetln!(self.s, "{t} =l div {d}, {size}", size = elem.size())?;
+            return Ok((t, Type::I64))
+        }
+
+        let (p, pty, i, ity) = if lty.is_ptr() { (l, lty, r, rty) } else { (r, rty, l, lty) };
+        let Type::Ptr(ref elem) = pty else { unreachable!() };
+        let i = self.convert(i, &ity, if ity.is_signed() { &Type::I64 } else { &Type::U64 })?;
+        let off = self.compile_offset(&i, elem)?;
+        let inst = if let Expr::Sub(..) = expr { "sub" } else { "add" };
+        let t = self.tmp();
+        writetln!(self.s, "{t} =l {inst} {p}, {off}")?;
+        Ok((t, pty))
+    }
+
+    /// Offset of the element at `i` in bytes, `i` being an `l`.
+    fn compile_offset(&mut self, i: &str, elem: &Type) -> std::io::Result::<String> {
+        if let Ok(i) = i.parse::<i64>() {
+            return Ok((i * elem.size() as i64).to_string())
+        }
+        let off = self.tmp();
+        writetln!(self.s, "{off} =l mul {i}, {size}", size = elem.size())?;
+        Ok(off)
+    }
+
+    /// Re-extends the result of an operation on an integer narrower than 32 bits, so it stays in range.
+    fn normalize(&mut self, op: String, ty: &Type) -> std::io::Result::<String> {
+        if !ty.is_int() || ty.size() >= 4 { return Ok(op) }
+        let t = self.tmp();
+        writetln!(self.s, "{t} =w ext{sign}{width} {op}",
+                  sign = if ty.is_signed() { "s" } else { "u" },
+                  width = if ty.size() == 1 { "b" } else { "h" })?;
+        Ok(t)
+    }
+
+    /// Constants are converted right here, rather than at runtime. Ints are stored as `i64`s, floats as their bits.
+    fn convert_const(op: &str, from: &Type, to: &Type) -> Option::<String> {
+        if !from.is_numeric() || !to.is_numeric() { return None }
+        let ret = if from.is_int() {
+            let v = op.parse::<i64>().ok()?;
+            let v = if *from == Type::U64 { v as u64 as i128 } else { v as i128 };
+            match to {
+                Type::F32 => (v as f32).to_bits().to_string(),
+                Type::F64 => (v as f64).to_bits().to_string(),
+                _ => to.wrap(v).to_string()
+            }
+        } else {
+            let bits = op.parse::<u64>().ok()?;
+            let v = if *from == Type::F32 { f32::from_bits(bits as u32) as f64 } else { f64::from_bits(bits) };
+            match to {
+                Type::F32 => (v as f32).to_bits().to_string(),
+                Type::F64 => v.to_bits().to_string(),
+                _ if to.is_signed() => to.wrap(v as i64 as i128).to_string(),
+                _ => to.wrap(v as u64 as i128).to_string()
+            }
+        };
+        Some(ret)
+    }
+
+    /// Converts `op` of type `from` to type `to`, type checking has made sure the conversion is allowed.
+    fn convert(&mut self, op: String, from: &Type, to: &Type) -> std::io::Result::<String> {
+        if from == to { return Ok(op) }
+
+        if let Some(c) = Self::convert_const(&op, from, to) {
+            return Ok(c)
+        }
+
+        if from.is_int() && to.is_int() {
+            if to.size() < 4 {
+                return self.normalize(op, to)
+            }
+
+            let inst = match (from.size(), to.size()) {
+                (_, 4) | (8, 8) => "copy",
+                _ if from.is_signed() => "extsw",
+                _ => "extuw",
             };
-            Ok(())
+            let t = self.tmp();
+            writetln!(self.s, "{t} ={ty} {inst} {op}", ty = to.to_il_str())?;
+            return Ok(t)
+        }
+
+        let inst = match (from, to) {
+            (Type::F32, Type::F64) => "exts",
+            (Type::F64, Type::F32) => "truncd",
+            _ if from.is_int() && to.is_float() => match (from.is_signed(), from.size() == 8) {
+                (true,  true)  => "sltof",
+                (false, true)  => "ultof",
+                (true,  false) => "swtof",
+                (false, false) => "uwtof",
+            }
+            (Type::F32, _) if to.is_int() => if to.is_signed() { "stosi" } else { "stoui" }
+            (Type::F64, _) if to.is_int() => if to.is_signed() { "dtosi" } else { "dtoui" }
+            _ => return Ok(op)
+        };
+        let t = self.tmp();
+        writetln!(self.s, "{t} ={ty} {inst} {op}", ty = to.to_il_str())?;
+        self.normalize(t, to)
+    }
+
+    /// Suffix of the `load` and `store` instructions, and item type of the `data`, matching the size of `ty`.
+    #[inline]
+    fn mem_suffix(ty: &Type) -> &'static str {
+        match ty {
+            Type::F32 => "s",
+            Type::F64 => "d",
+            _ => match ty.size() {
+                1 => "b",
+                2 => "h",
+                4 => "w",
+                _ => "l",
+            }
+        }
+    }
+
+    #[inline]
+    fn data_item(ty: &Type) -> &'static str {
+        // floats are written as their bits, so the item type only has to match the size
+        match ty {
+            Type::F32 => "w",
+            Type::F64 => "l",
+            _ => Self::mem_suffix(ty),
+        }
+    }
+
+    /// Aggregates aren't loaded, the address is the value.
+    fn load(&mut self, addr: &str, ty: &Type) -> std::io::Result::<String> {
+        if ty.is_aggregate() { return Ok(addr.to_owned()) }
+        let t = self.tmp();
+        let inst = match ty.size() {
+            1 | 2 if ty.is_signed() => format!("loads{m}", m = Self::mem_suffix(ty)),
+            1 | 2 => format!("loadu{m}", m = Self::mem_suffix(ty)),
+            _ => format!("load{m}", m = Self::mem_suffix(ty)),
+        };
+        writetln!(self.s, "{t} ={il} {inst} {addr}", il = ty.to_il_str())?;
+        Ok(t)
+    }
+
+    /// Aggregates are copied into the memory at `addr`.
+    #[inline]
+    fn store(&mut self, addr: &str, ty: &Type, op: &str) -> std::io::Result::<()> {
+        if ty.is_aggregate() {
+            return writetln!(self.s, "blit {op}, {addr}, {size}", size = ty.size())
+        }
+        writetln!(self.s, "store{m} {op}, {addr}", m = Self::mem_suffix(ty))
+    }
+
+    fn load_global(&mut self, name: &str, ty: &Type) -> std::io::Result::<String> {
+        let t = self.load(&format!("${name}"), ty)?;
+        if let Type::Str = ty {
+            writetln!(self.s, "{t}.len =l loadl ${name}.len")?;
+        }
+        Ok(t)
+    }
+
+    fn store_global(&mut self, name: &str, ty: &Type, op: &str) -> std::io::Result::<()> {
+        self.store(&format!("${name}"), ty, op)?;
+        if let Type::Str = ty {
+            writetln!(self.s, "storel {op}.len, ${name}.len")?;
+        }
+        Ok(())
+    }
+
+    /// Aggregates are allocated in multiples of 8 bytes, so they can be zeroed with `storel`s.
+    #[inline(always)]
+    fn alloc_size(ty: &Type) -> usize {
+        ty.size().next_multiple_of(8)
+    }
+
+    fn compile_zero(&mut self, addr: &str, size: usize) -> std::io::Result::<()> {
+        let n = self.lbl();
+        let i = self.tmp();
+        writetln!(self.s, "{i} =l copy 0")?;
+        writeln!(self.s, "@zero.loop.{n}")?;
+        let p = self.tmp();
+        writetln!(self.s, "{p} =l add {addr}, {i}")?;
+        writetln!(self.s, "storel 0, {p}")?;
+        writetln!(self.s, "{i} =l add {i}, 8")?;
+        let c = self.tmp();
+        writetln!(self.s, "{c} =w cultl {i}, {size}")?;
+        writetln!(self.s, "jnz {c}, @zero.loop.{n}, @zero.end.{n}")?;
+        writeln!(self.s, "@zero.end.{n}")?;
+        Ok(())
+    }
+
+    /// Allocates a fresh array on the stack, storing every element converted to `elem`.
+    fn compile_array(&mut self, items: &[Box::<Expr>], elem: &Type, ctx: &Box::<Ctx>) -> std::io::Result::<String> {
+        let arr = self.tmp();
+        writetln!(self.s, "{arr} =l alloc8 {size}", size = Self::alloc_size(&Type::Array(Box::new(elem.to_owned()), items.len())))?;
+        for (idx, item) in items.iter().enumerate() {
+            let (op, ty) = self.compile_expr(item, ctx)?;
+            let op = self.convert(op, &ty, elem)?;
+            let p = self.tmp();
+            writetln!(self.s, "{p} =l add {arr}, {off}", off = idx * elem.size())?;
+            self.store(&p, elem, &op)?;
+        }
+        Ok(arr)
+    }
+
+    /// Allocates a fresh struct on the stack, storing every field at its offset.
+    fn compile_struct(&mut self, fields: &[(Box::<Token>, Box::<Expr>)], def: &StructDef, ctx: &Box::<Ctx>) -> std::io::Result::<String> {
+        let s = self.tmp();
+        writetln!(self.s, "{s} =l alloc8 {size}", size = def.size.next_multiple_of(8))?;
+        for (field_token, value) in fields.iter() {
+            let field = def.field(field_token.string).unwrap();
+            let op = self.compile_value(value, &field.ty, ctx)?;
+            let p = self.tmp();
+            writetln!(self.s, "{p} =l add {s}, {off}", off = field.offset)?;
+            self.store(&p, &field.ty, &op)?;
+        }
+        Ok(s)
+    }
+
+    /// Allocates a fresh enum on the stack, storing the tag of the variant and its payload.
+    fn compile_variant(&mut self, variant_token: &Token, args: &[Box::<Expr>], def: &EnumDef, ctx: &Box::<Ctx>) -> std::io::Result::<String> {
+        let (tag, variant) = def.variant(variant_token.string).unwrap();
+        let e = self.tmp();
+        writetln!(self.s, "{e} =l alloc8 {size}", size = def.size.next_multiple_of(8))?;
+        writetln!(self.s, "storel {tag}, {e}")?;
+        for (arg, (ty, off)) in args.iter().zip(variant.fields.iter()) {
+            let op = self.compile_value(arg, ty, ctx)?;
+            let p = self.tmp();
+            writetln!(self.s, "{p} =l add {e}, {off}")?;
+            self.store(&p, ty, &op)?;
+        }
+        Ok(e)
+    }
+
+    /// Address of the element of the array `base` evaluates to at `index`. Unless they're disabled,
+    /// indices are checked against the length at runtime, constant ones have been checked by the parser already.
+    fn compile_elem_addr(&mut self, base: &Box::<Expr>, index: &Box::<Expr>, loc: &Loc, ctx: &Box::<Ctx>) -> std::io::Result::<(String, Type)> {
+        let (arr, ty) = self.compile_expr(base, ctx)?;
+        let Type::Array(elem, len) = ty else { unreachable!() };
+
+        let (i, ity) = self.compile_expr(index, ctx)?;
+        let i = self.convert(i, &ity, if ity.is_signed() { &Type::I64 } else { &Type::U64 })?;
+
+        if self.bounds_checks && !index.is_const() {
+            let n = self.lbl();
+            let c = self.tmp();
+            // negative indices wrap around to huge unsigned ones, so a single comparison catches them too
+            writetln!(self.s, "{c} =w cultl {i}, {len}")?;
+            writetln!(self.s, "jnz {c}, @index.ok.{n}, @index.oob.{n}")?;
+            writeln!(self.s, "@index.oob.{n}")?;
+            let loc = loc.to_string();
+            let id = self.append_str(loc.as_bytes());
+            writetln!(self.s, "call $panic_bounds(l $str{id}, l {loc_len}, l {i}, l {len})", loc_len = loc.len())?;
+            writeln!(self.s, "@index.ok.{n}")?;
+        }
+
+        let off = self.compile_offset(&i, &elem)?;
+        let addr = self.tmp();
+        writetln!(self.s, "{addr} =l add {arr}, {off}")?;
+        Ok((addr, *elem))
+    }
+
+    /// Lowers `value` converted to `ty`, array literals are stored with the element type of the array they initialize.
+    fn compile_value(&mut self, value: &Box::<Expr>, ty: &Type, ctx: &Box::<Ctx>) -> std::io::Result::<String> {
+        match (value.deref(), ty) {
+            (Expr::Array(items), Type::Array(elem, _)) => return self.compile_array(items, elem, ctx),
+            (Expr::Struct(_, fields), Type::Struct(def)) => return self.compile_struct(fields, def, ctx),
+            (Expr::Variant(_, variant_token, args), Type::Enum(def)) => return self.compile_variant(variant_token, args, def, ctx),
+            _ => {}
         }
-        compile_expr(&mut self.s, ctx, &vd.value)
+        let (op, vty) = self.compile_expr(value, ctx)?;
+        self.convert(op, &vty, ty)
     }
 
-    fn compile_fn(&mut self, fn_: &Fn, ctx: &Box::<Ctx>) -> std::io::Result::<()> {
+    /// Globals live in `data`, their initializer has been folded into a constant by the parser,
+    /// unless it's a script, then it's zeroed and the initializer is stored by [`Compiler::compile_global_init`].
+    fn compile_global(&mut self, vd: &VarDecl, ctx: &Box::<Ctx>) -> std::io::Result::<()> {
+        let ref name = vd.il_name;
+        let Some(value) = vd.value.as_ref().filter(|value| value.is_const()) else {
+            writeln!(self.s, "data ${name} = {{ z {size} }}", size = vd.ty.size())?;
+            if let Type::Str = vd.ty {
+                writeln!(self.s, "data ${name}.len = {{ z 8 }}")?;
+            }
+            return Ok(())
+        };
+
+        if let Expr::Str(bytes) = value.deref() {
+            let id = self.append_str(bytes);
+            writeln!(self.s, "data ${name} = {{ l $str{id} }}")?;
+            writeln!(self.s, "data ${name}.len = {{ l {len} }}", len = bytes.len())?;
+            return Ok(())
+        }
+
+        let mut items = Vec::new();
+        self.compile_data_items(value, &vd.ty, ctx, &mut items)?;
+        writeln!(self.s, "data ${name} = {{ {items} }}", items = items.join(", "))?;
+        Ok(())
+    }
+
+    /// Items of the `data` holding the constant `value`, the padding between the fields of structs is zeroed.
+    fn compile_data_items(&mut self, value: &Box::<Expr>, ty: &Type, ctx: &Box::<Ctx>, items: &mut Vec::<String>) -> std::io::Result::<()> {
+        match (value.deref(), ty) {
+            (Expr::Array(values), Type::Array(elem, _)) => for value in values.iter() {
+                self.compile_data_items(value, elem, ctx, items)?
+            }
+            (Expr::Struct(_, values), Type::Struct(def)) => {
+                let mut end = 0;
+                for field in def.fields.iter() {
+                    if field.offset > end { items.push(format!("z {n}", n = field.offset - end)) }
+                    let (_, value) = values.iter().find(|(field_token, _)| field_token.string == field.name).unwrap();
+                    self.compile_data_items(value, &field.ty, ctx, items)?;
+                    end = field.offset + field.ty.size();
+                }
+                if def.size > end { items.push(format!("z {n}", n = def.size - end)) }
+            }
+            (Expr::Variant(_, variant_token, args), Type::Enum(def)) => {
+                let (tag, variant) = def.variant(variant_token.string).unwrap();
+                items.push(format!("l {tag}"));
+                let mut end = EnumDef::TAG_SIZE;
+                for (arg, (ty, off)) in args.iter().zip(variant.fields.iter()) {
+                    if *off > end { items.push(format!("z {n}", n = off - end)) }
+                    self.compile_data_items(arg, ty, ctx, items)?;
+                    end = off + ty.size();
+                }
+                if def.size > end { items.push(format!("z {n}", n = def.size - end)) }
+            }
+            _ => {
+                // constants are lowered without emitting any instructions, so this is fine outside of a function
+                let (op, vty) = self.compile_expr(value, ctx)?;
+                let op = self.convert(op, &vty, ty)?;
+                items.push(format!("{item} {op}", item = Self::data_item(ty)))
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_global_init(&mut self, vd: &VarDecl, ctx: &Box::<Ctx>) -> std::io::Result::<()> {
+        let Some(ref value) = vd.value else { return Ok(()) };
+        let op = self.compile_value(value, &vd.ty, ctx)?;
+        self.store_global(&vd.il_name, &vd.ty, &op)
+    }
+
+    fn compile_var_decl(&mut self, vd: &VarDecl, ctx: &Box::<Ctx>) -> std::io::Result::<()> {
+        if vd.global { return self.compile_global(vd, ctx) }
+
+        let ref name = vd.il_name;
+        let op = match (&vd.value, &vd.ty) {
+            // array and struct literals are allocated fresh, so there's nothing to copy
+            (Some(value), _) if matches!(value.deref(), Expr::Array(..) | Expr::Struct(..) | Expr::Variant(..)) => self.compile_value(value, &vd.ty, ctx)?,
+            (Some(value), ty) if ty.is_aggregate() => {
+                let op = self.compile_value(value, &vd.ty, ctx)?;
+                writetln!(self.s, "%{name} =l alloc8 {size}", size = Self::alloc_size(&vd.ty))?;
+                return writetln!(self.s, "blit {op}, %{name}, {size}", size = vd.ty.size())
+            }
+            (Some(value), _) if vd.stack => {
+                let op = self.compile_value(value, &vd.ty, ctx)?;
+                writetln!(self.s, "%{name}.addr =l alloc8 8")?;
+                return self.store(&format!("%{name}.addr"), &vd.ty, &op)
+            }
+            (Some(value), _) => self.compile_value(value, &vd.ty, ctx)?,
+            (None, _) => {
+                let size = Self::alloc_size(&vd.ty);
+                writetln!(self.s, "%{name} =l alloc8 {size}")?;
+                return self.compile_zero(&format!("%{name}"), size)
+            }
+        };
+        writetln!(self.s, "%{name} ={ty} copy {op}", ty = vd.ty.to_il_str())?;
+        if let Type::Str = vd.ty {
+            writetln!(self.s, "%{name}.len =l copy {op}.len")?;
+        }
+        Ok(())
+    }
+
+    fn compile_assign(&mut self, assign: &Assign, ctx: &Box::<Ctx>) -> std::io::Result::<()> {
+        let (addr, ty) = match assign.target.deref() {
+            Expr::Lit(lit) => {
+                let sym = &ctx.sym_map()[lit.string];
+                let op = self.compile_value(&assign.value, &sym.ty, ctx)?;
+                let ref name = sym.il_name;
+                match sym.kind {
+                    SymKind::Local if sym.ty.is_aggregate() => self.store(&format!("%{name}"), &sym.ty, &op)?,
+                    SymKind::Local => {
+                        writetln!(self.s, "%{name} ={ty} copy {op}", ty = sym.ty.to_il_str())?;
+                        if let Type::Str = sym.ty {
+                            writetln!(self.s, "%{name}.len =l copy {op}.len")?;
+                        }
+                    }
+                    SymKind::Stack => self.store(&format!("%{name}.addr"), &sym.ty, &op)?,
+                    SymKind::Global => self.store_global(name, &sym.ty, &op)?,
+                    SymKind::Const(..) | SymKind::Type => unreachable!()
+                }
+                return Ok(())
+            }
+            _ => self.compile_place_addr(&assign.target, ctx)?
+        };
+
+        let op = self.compile_value(&assign.value, &ty, ctx)?;
+        self.store(&addr, &ty, &op)
+    }
+
+    /// Structs and enums are passed and returned by value as QBE aggregates, which copies them.
+    #[inline]
+    fn abi_ty(ty: &Type) -> String {
+        match ty {
+            Type::Struct(..) | Type::Enum(..) => format!(":{ty}"),
+            _ => ty.to_il_str().to_owned()
+        }
+    }
+
+    /// Type of a member of an aggregate type.
+    #[inline]
+    fn member_ty(ty: &Type) -> String {
+        match ty {
+            Type::Struct(..) | Type::Enum(..) => Self::abi_ty(ty),
+            _ => Self::mem_suffix(ty).to_owned()
+        }
+    }
+
+    /// Emits `type :Name = { ... }`, QBE lays the fields out with the same alignment as [`StructDef`].
+    fn compile_struct_def(&mut self, def: &StructDef) -> std::io::Result::<()> {
+        let fields = def.fields.iter().map(|field| Self::member_ty(&field.ty)).collect::<Vec::<_>>();
+        writeln!(self.s, "type :{name} = {{ {fields} }}", name = def.name, fields = fields.join(", "))
+    }
+
+    /// Enums are unions of the variants, each one being the tag followed by its payload.
+    fn compile_enum_def(&mut self, def: &EnumDef) -> std::io::Result::<()> {
+        write!(self.s, "type :{name} = {{", name = def.name)?;
+        for variant in def.variants.iter() {
+            write!(self.s, " {{ l")?;
+            for (ty, _) in variant.fields.iter() {
+                write!(self.s, ", {ty}", ty = Self::member_ty(ty))?;
+            }
+            write!(self.s, " }}")?;
+        }
+        writeln!(self.s, " }}")
+    }
+
+    /// Lowered to a chain of comparisons of the tag, the bindings of an arm are copied out of the payload before its body runs.
+    fn compile_match(&mut self, match_: &Match, ctx: &Box::<Ctx>) -> std::io::Result::<()> {
+        let (e, _) = self.compile_expr(&match_.value, ctx)?;
+        let tag = self.tmp();
+        writetln!(self.s, "{tag} =l loadl {e}")?;
+
+        let n = self.lbl();
+        for (idx, arm) in match_.arms.iter().enumerate() {
+            if let Some(variant) = arm.variant {
+                let c = self.tmp();
+                writetln!(self.s, "{c} =w ceql {tag}, {variant}")?;
+                writetln!(self.s, "jnz {c}, @match.arm.{n}.{idx}, @match.next.{n}.{idx}")?;
+                writeln!(self.s, "@match.arm.{n}.{idx}")?;
+
+                let ref fields = match_.def.variants[variant].fields;
+                for (vd, (ty, off)) in arm.bindings.iter().zip(fields.iter()) {
+                    let Some(vd) = vd else { continue };
+                    let ref name = vd.il_name;
+                    let p = self.tmp();
+                    writetln!(self.s, "{p} =l add {e}, {off}")?;
+                    let op = self.load(&p, ty)?;
+                    if ty.is_aggregate() {
+                        writetln!(self.s, "%{name} =l alloc8 {size}", size = Self::alloc_size(ty))?;
+                        self.store(&format!("%{name}"), ty, &op)?;
+                    } else if vd.stack {
+                        writetln!(self.s, "%{name}.addr =l alloc8 8")?;
+                        self.store(&format!("%{name}.addr"), ty, &op)?;
+                    } else {
+                        writetln!(self.s, "%{name} ={il} copy {op}", il = ty.to_il_str())?;
+                    }
+                }
+            }
+
+            for ast in arm.body.iter() {
+                self.compile_ast(ast)?;
+            }
+            writetln!(self.s, "jmp @match.end.{n}")?;
+
+            if arm.variant.is_some() {
+                writeln!(self.s, "@match.next.{n}.{idx}")?;
+            }
+        }
+        writeln!(self.s, "@match.end.{n}")?;
+        Ok(())
+    }
+
+    fn compile_fn(&mut self, fn_: &Fn) -> std::io::Result::<()> {
         write!(self.s, "function")?;
         let ret_ty = fn_.ret_ty.as_ref()
-            .map(Type::to_il_str)
+            .map(Self::abi_ty)
             .unwrap_or_default();
 
         write!(self.s, " {ret_ty} ${name}(", name = fn_.name_token.string)?;
         for (idx, arg) in fn_.args.iter().enumerate() {
             let ref name = arg.name_token.string;
-            write!(self.s, "{ty} %{name}", ty = arg.ty.to_il_str())?;
+            write!(self.s, "{ty} %{name}", ty = Self::abi_ty(&arg.ty))?;
+            if let Type::Str = arg.ty { write!(self.s, ", l %{name}.len")?; }
             if idx + 1 < fn_.args.len() { write!(self.s, ", ")?; }
         }
 
         writeln!(self.s, ") {{")?;
         writeln!(self.s, "@start")?;
+        for arg in fn_.args.iter().filter(|arg| arg.stack) {
+            let ref name = arg.name_token.string;
+            writetln!(self.s, "%{name}.addr =l alloc8 8")?;
+            self.store(&format!("%{name}.addr"), &arg.ty, &format!("%{name}"))?;
+        }
         for ast in fn_.body.iter() {
             self.compile_ast(ast)?;
         }
 
         match fn_.ret_ty {
-            Some(Type::I64)  => writetln!(self.s, "ret 0")?,
-            Some(Type::F64)  => writetln!(self.s, "ret 0")?,
+            // aggregates are returned through their address, so falling off the end returns a zeroed one
+            Some(ref ty) if ty.is_aggregate() => {
+                let size = Self::alloc_size(ty);
+                let t = self.tmp();
+                writetln!(self.s, "{t} =l alloc8 {size}")?;
+                self.compile_zero(&t, size)?;
+                writetln!(self.s, "ret {t}")?
+            }
+            Some(..) => writetln!(self.s, "ret 0")?,
             None => writetln!(self.s, "ret")?
         };
         writeln!(self.s, "}}")?;
@@ -91,69 +868,221 @@ impl Compiler {
         Ok(())
     }
 
+    fn compile_print_arg(&mut self, arg: &Box::<Expr>, ctx: &Box::<Ctx>, newline: bool, fd: u8, precision: usize) -> std::io::Result::<()> {
+        let nl = newline as u8;
+        let (op, ty) = self.compile_expr(arg, ctx)?;
+        match ty {
+            _ if ty.is_signed() => {
+                let op = self.convert(op, &ty, &Type::I64)?;
+                writetln!(self.s, "call $print_i64(l {op}, w {nl}, w {fd})")?
+            }
+            _ if ty.is_int() => {
+                let op = self.convert(op, &ty, &Type::U64)?;
+                writetln!(self.s, "call $print_u64(l {op}, w {nl}, w {fd})")?
+            }
+            Type::F32  => writetln!(self.s, "call $print_f32(s {op}, w {nl}, w {fd}, w {precision})")?,
+            Type::F64  => writetln!(self.s, "call $print_f64(d {op}, w {nl}, w {fd}, w {precision})")?,
+            Type::Str  => writetln!(self.s, "call $print_str(l {op}, l {op}.len, w {nl}, w {fd})")?,
+            Type::Bool => writetln!(self.s, "call $print_bool(w {op}, w {nl}, w {fd})")?,
+            Type::Char => writetln!(self.s, "call $print_char(w {op}, w {nl}, w {fd})")?,
+            Type::Ptr(..) => writetln!(self.s, "call $print_u64(l {op}, w {nl}, w {fd})")?,
+            _ => unreachable!()
+        };
+        Ok(())
+    }
+
     fn compile_print(&mut self, fc: &FnCall, ctx: &Box::<Ctx>) -> std::io::Result::<()> {
-        fn get_type(lit: &Box::<Token>, ctx: &Box::<Ctx>) -> Option::<Type> {
-            if let Some(ref sym) = ctx.sym_map().get(lit.string) {
-                let AstKind::VarDecl(ref vd) = &sym.kind else { todo!() };
-                match vd.value.deref() {
-                    Expr::I64(..) => Some(Type::I64),
-                    Expr::F64(..) => Some(Type::F64),
-                    Expr::Lit(lit) => get_type(lit, ctx),
-                    _ => todo!()
-                }
-            } else { None }
+        for arg in fc.args.iter() {
+            self.compile_print_arg(arg, ctx, true, STDOUT, DEFAULT_PRECISION)?;
         }
+        Ok(())
+    }
 
-        for arg in fc.args.iter() {
-            match arg.deref() {
-                Expr::Lit(lit) => if let Some(ty) = get_type(lit, ctx) {
-                    let ref string = lit.string;
-                    match ty {
-                        Type::I64 => writetln!(self.s, "call $print_i64(l %{string}, w 1)")?,
-                        Type::F64 => writetln!(self.s, "call $print_f64(d %{string}, w 1)")?
+    /// Lowers `println`, `eprint` and `eprintln`: the format string is checked against the arguments here,
+    /// at compile time, so the runtime only ever sees plain `print_*` calls.
+    fn compile_print_fmt(&mut self, fc: &FnCall, ctx: &Box::<Ctx>, fd: u8, newline: bool) -> std::io::Result::<()> {
+        let ref loc = fc.name_token.loc;
+        let name = fc.name_token.string;
+
+        let Some(Expr::Str(fmt)) = fc.args.first().map(Deref::deref) else {
+            panic!("{loc} error: `{name}` expects a string literal as its first argument")
+        };
+
+        let mut pieces = FmtPiece::parse(fmt, loc);
+        if newline {
+            match pieces.last_mut() {
+                Some(FmtPiece::Str(bytes)) => bytes.push(b'\n'),
+                _ => pieces.push(FmtPiece::Str(vec![b'\n']))
+            }
+        }
+
+        let args = &fc.args[1..];
+        let placeholders = pieces.iter().filter(|p| matches!(p, FmtPiece::Arg {..})).count();
+        if placeholders != args.len() {
+            panic!("{loc} error: format string of `{name}` has {placeholders} placeholder(s), but {n} argument(s) were supplied",
+                   n = args.len())
+        }
+
+        let mut args = args.iter();
+        for piece in pieces.iter() {
+            match piece {
+                FmtPiece::Str(bytes) => {
+                    let id = self.append_str(bytes);
+                    writetln!(self.s, "call $print_str(l $str{id}, l {len}, w 0, w {fd})", len = bytes.len())?
+                }
+                FmtPiece::Arg { precision } => {
+                    let arg = args.next().unwrap();
+                    let ty = arg.type_check(ctx.sym_map(), loc);
+                    if precision.is_some() && !ty.is_float() {
+                        panic!("{loc} error: precision in format string of `{name}` is only supported for float arguments, but got: {ty}")
                     }
-                },
-                Expr::I64(int) => writetln!(self.s, "call $print_i64(l {int}, w 1)")?,
-                Expr::F64(flt) => writetln!(self.s, "call $print_f64(d {bits}, w 1)",
-                                             bits = flt.to_bits())?,
-                _ => todo!(),
-            };
+                    self.compile_print_arg(arg, ctx, false, fd, precision.unwrap_or(DEFAULT_PRECISION))?
+                }
+            }
         }
         Ok(())
     }
 
+    /// Lowers a call to a builtin implemented by the runtime, returning its result, if it has one.
+    fn compile_builtin(&mut self, name: &str, args: &[Box::<Expr>], ctx: &Box::<Ctx>) -> std::io::Result::<Option::<(String, Type)>> {
+        let mut ops = Vec::with_capacity(args.len());
+        for arg in args.iter() {
+            let (op, ty) = self.compile_expr(arg, ctx)?;
+            // every argument is passed as an `l`, bools and chars are zero extended like `u8`s
+            let int = if matches!(ty, Type::Bool | Type::Char) { Type::U8 } else { ty.to_owned() };
+            let op = if int.is_int() { self.convert(op, &int, if int.is_signed() { &Type::I64 } else { &Type::U64 })? } else { op };
+            ops.push((op, ty));
+        }
+
+        let ret = match name {
+            "alloc" => Some(Type::Ptr(Box::new(Type::U8))),
+            "realloc" => Some(ops[0].1.to_owned()),
+            "read_f64" => Some(Type::F64),
+            "free" => None,
+            _ => Some(Type::I64)
+        };
+
+        // there's a wrapper for every number of arguments in the runtime
+        let name = if name == "syscall" { format!("syscall{n}", n = ops.len() - 1) } else { name.to_owned() };
+
+        // paths are passed along with their length, the runtime terminates them
+        let args = ops.iter().map(|(op, ty)| match ty {
+            Type::Str if name == "open" => format!("l {op}, l {op}.len"),
+            _ => format!("l {op}")
+        }).collect::<Vec::<_>>().join(", ");
+        match ret {
+            Some(ty) => {
+                let t = self.tmp();
+                writetln!(self.s, "{t} ={il} call ${name}({args})", il = ty.to_il_str())?;
+                Ok(Some((t, ty)))
+            }
+            None => {
+                writetln!(self.s, "call ${name}({args})")?;
+                Ok(None)
+            }
+        }
+    }
+
     fn compile_fn_call(&mut self, fc: &FnCall, ctx: &Box::<Ctx>) -> std::io::Result::<()> {
-        if fc.name_token.string.eq("print") {
-            return self.compile_print(fc, ctx)
+        match fc.name_token.string {
+            "print"    => self.compile_print(fc, ctx),
+            "println"  => self.compile_print_fmt(fc, ctx, STDOUT, true),
+            "eprint"   => self.compile_print_fmt(fc, ctx, STDERR, false),
+            "eprintln" => self.compile_print_fmt(fc, ctx, STDERR, true),
+            name if BUILTINS.contains(&name) => self.compile_builtin(name, &fc.args, ctx).map(|_| ()),
+            // the result, if there's one, is just dropped
+            name => self.compile_call(name, &fc.args, None, ctx).map(|_| ())
         }
+    }
 
-        writet!(self.s, "call ${name}(", name = fc.name_token.string)?;
-        for (idx, arg) in fc.args.iter().enumerate() {
-            match arg.deref() {
-                Expr::I64(int) => write!(self.s, "l {int}")?,
-                Expr::F64(flt) => write!(self.s, "d {bits}",
-                                         bits = flt.to_bits())?,
-                _ => todo!(),
-            };
-            if idx + 1 < fc.args.len() { write!(self.s, ", ")?; }
+    /// Lowers a call to a function of the file, returning the temporary holding its result, when `ret_ty` is given.
+    fn compile_call(&mut self, name: &str, args: &[Box::<Expr>], ret_ty: Option::<&Type>, ctx: &Box::<Ctx>) -> std::io::Result::<Option::<String>> {
+        let args = args.iter().map(|arg| {
+            self.compile_expr(arg, ctx)
+        }).collect::<std::io::Result::<Vec::<_>>>()?;
+
+        let ret = match ret_ty {
+            Some(ty) => {
+                let t = self.tmp();
+                writet!(self.s, "{t} ={ty} call ${name}(", ty = Self::abi_ty(ty))?;
+                Some(t)
+            }
+            None => {
+                writet!(self.s, "call ${name}(")?;
+                None
+            }
+        };
+        for (idx, (op, ty)) in args.iter().enumerate() {
+            write!(self.s, "{ty} {op}", ty = Self::abi_ty(ty))?;
+            if let Type::Str = ty { write!(self.s, ", l {op}.len")?; }
+            if idx + 1 < args.len() { write!(self.s, ", ")?; }
         }
         writeln!(self.s, ")")?;
-        Ok(())
+        Ok(ret)
+    }
+
+    /// `ret` ends the block, so anything after it starts a new one, that's never jumped to.
+    fn compile_return(&mut self, value: Option::<&Box::<Expr>>, ctx: &Box::<Ctx>) -> std::io::Result::<()> {
+        match value {
+            Some(value) => {
+                let (op, _) = self.compile_expr(value, ctx)?;
+                writetln!(self.s, "ret {op}")?
+            }
+            None => writetln!(self.s, "ret")?
+        };
+        let n = self.lbl();
+        writeln!(self.s, "@ret.{n}")
     }
 
     fn compile_ast(&mut self, ast: &Ast) -> std::io::Result::<()> {
         match &ast.kind {
-            AstKind::Fn(fn_)     => self.compile_fn(&fn_, &ast.ctx),
+            AstKind::Fn(fn_)     => self.compile_fn(&fn_),
             AstKind::VarDecl(vd) => self.compile_var_decl(&vd, &ast.ctx),
-            AstKind::FnCall(fc)  => self.compile_fn_call(&fc, &ast.ctx)
+            AstKind::Assign(as_) => self.compile_assign(&as_, &ast.ctx),
+            AstKind::FnCall(fc)  => self.compile_fn_call(&fc, &ast.ctx),
+            AstKind::Block(body) => body.iter().try_for_each(|ast| self.compile_ast(ast)),
+            AstKind::Struct(def) => self.compile_struct_def(def),
+            AstKind::Enum(def)   => self.compile_enum_def(def),
+            AstKind::Match(m)    => self.compile_match(m, &ast.ctx),
+            AstKind::Return(value) => self.compile_return(value.as_ref(), &ast.ctx),
         }
     }
 
+    /// Lowers the top-level statements of a script, in order, into the implicit `main`.
+    fn compile_script(&mut self, asts: &Asts) -> std::io::Result::<()> {
+        writeln!(self.s, "function ${ENTRY}() {{")?;
+        writeln!(self.s, "@start")?;
+        for ast in asts.asts.iter().filter(|ast| ast.is_stmt()) {
+            match &ast.kind {
+                AstKind::VarDecl(vd) => self.compile_global_init(vd, &ast.ctx)?,
+                _ => self.compile_ast(ast)?
+            }
+        }
+        writetln!(self.s, "ret")?;
+        writeln!(self.s, "}}")?;
+        Ok(())
+    }
+
     pub fn compile(&mut self, asts: Asts) -> std::io::Result::<()> {
+        // types have to be defined before they're used
+        for ast in asts.asts.iter().filter(|ast| matches!(ast.kind, AstKind::Struct(..) | AstKind::Enum(..))) {
+            self.compile_ast(ast)?;
+        }
+
+        let mut script = true;
         for ast in asts.asts.iter() {
+            match &ast.kind {
+                AstKind::Fn(fn_) if fn_.name_token.string == ENTRY => script = false,
+                AstKind::Fn(..) | AstKind::VarDecl(..) => {}
+                _ => continue
+            }
             self.compile_ast(ast)?;
         }
 
+        if script {
+            self.compile_script(&asts)?;
+        }
+
         writeln!(self.s, "export function w $_start() {{")?;
         writeln!(self.s, "@start")?;
         writetln!(self.s, "%argc =l call $argc()")?;
@@ -162,6 +1091,6 @@ impl Compiler {
         writetln!(self.s, "ret")?;
         writeln!(self.s, "}}")?;
 
-        Ok(())
+        self.compile_strs()
     }
 }
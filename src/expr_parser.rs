@@ -1,20 +1,27 @@
-use super::ast::Expr;
-use crate::{ast::AstKind, SymMap, Token, TokenKind, TokensRefs};
+use super::ast::{Expr, Type};
+use crate::{lexer::NumLit, FnMap, Lexer, SymKind, SymMap, Token, TokenKind, TokensRefs, BUILTINS};
 
-pub struct ExprParser<'a> {
+/// Constructor of the node of a binary operator, from its operands.
+type BinOp<'a> = fn(Box::<Expr<'a>>, Box::<Expr<'a>>) -> Expr<'a>;
+
+pub struct ExprParser<'a, 'b> {
+    eof: bool,
     curr_idx: usize,
     curr_token: Box::<Token<'a>>,
-    sym_map: &'a SymMap<'a>,
-    tokens: TokensRefs<'a>,
+    sym_map: &'b SymMap<'a>,
+    fns: &'b FnMap<'a>,
+    tokens: TokensRefs<'a, 'b>,
 }
 
-impl<'a> ExprParser<'a> {
+impl<'a, 'b> ExprParser<'a, 'b> {
     #[inline]
-    pub fn new(tokens: TokensRefs<'a>, sym_map: &'a SymMap<'a>) -> Self {
+    pub fn new(tokens: TokensRefs<'a, 'b>, sym_map: &'b SymMap<'a>, fns: &'b FnMap<'a>) -> Self {
         ExprParser {
             curr_token: tokens[0].to_owned(),
             tokens,
             sym_map,
+            fns,
+            eof: false,
             curr_idx: 1,
         }
     }
@@ -22,6 +29,7 @@ impl<'a> ExprParser<'a> {
     #[inline]
     fn accept_it(&mut self) -> bool {
         if self.curr_idx == self.tokens.len() {
+            self.eof = true;
             true
         } else {
             self.curr_token = self.tokens[self.curr_idx].to_owned();
@@ -45,71 +53,205 @@ impl<'a> ExprParser<'a> {
     // start ::= expr
     pub fn parse(&mut self) -> Box::<Expr<'a>> {
         let program_ast = self.parse_expr();
-        assert!(self.tokens.len() == self.curr_idx);
+        if !self.eof {
+            panic!("{loc} error: unexpected token in expression: {string}",
+                   loc = self.curr_token.loc,
+                   string = self.curr_token.string)
+        }
         program_ast
     }
 
-    // expr ::= term (+ expr | - expr | epsilon)
+    /// Binding power and constructor of every binary operator, from the loosest to the tightest.
+    /// All of them are left-associative.
+    fn binary_op(kind: &TokenKind) -> Option::<(u8, BinOp<'a>)> {
+        let op = match kind {
+            TokenKind::DoublePipe          => (1,  Expr::Or as BinOp),
+            TokenKind::DoubleAmpersand     => (2,  Expr::And as _),
+            TokenKind::Pipe                => (3,  Expr::BitOr as _),
+            TokenKind::Caret               => (4,  Expr::BitXor as _),
+            TokenKind::Ampersand           => (5,  Expr::BitAnd as _),
+            TokenKind::DoubleEqual         => (6,  Expr::Eq as _),
+            TokenKind::BangEqual           => (6,  Expr::Ne as _),
+            TokenKind::LAngleBracket       => (7,  Expr::Lt as _),
+            TokenKind::LAngleBracketEqual  => (7,  Expr::Le as _),
+            TokenKind::RAngleBracket       => (7,  Expr::Gt as _),
+            TokenKind::RAngleBracketEqual  => (7,  Expr::Ge as _),
+            TokenKind::DoubleLAngleBracket => (8,  Expr::Shl as _),
+            TokenKind::DoubleRAngleBracket => (8,  Expr::Shr as _),
+            TokenKind::Plus                => (9,  Expr::Add as _),
+            TokenKind::Minus               => (9,  Expr::Sub as _),
+            TokenKind::Asterisk            => (10, Expr::Mul as _),
+            TokenKind::Slash               => (10, Expr::Div as _),
+            TokenKind::Percent             => (10, Expr::Rem as _),
+            _ => return None
+        };
+        Some(op)
+    }
+
+    // expr ::= binary(0)
+    #[inline]
     fn parse_expr(&mut self) -> Box::<Expr<'a>> {
-        let term_ast = self.parse_term();
+        self.parse_binary(0)
+    }
+
+    // binary(p) ::= cast (op binary(prec(op) + 1))*, where prec(op) >= p
+    fn parse_binary(&mut self, min_prec: u8) -> Box::<Expr<'a>> {
+        let mut lhs_ast = self.parse_cast();
+
+        while let Some((prec, op)) = Self::binary_op(&self.curr_token.kind) {
+            if prec < min_prec || self.eof { break }
+            self.accept_it();
+            // binding the rhs tighter makes operators of the same precedence associate to the left
+            let rhs_ast = self.parse_binary(prec + 1);
+            lhs_ast = Box::new(op(lhs_ast, rhs_ast));
+        }
+
+        lhs_ast
+    }
+
+    // cast ::= factor (as type)*
+    fn parse_cast(&mut self) -> Box::<Expr<'a>> {
+        let mut factor_ast = self.parse_factor();
+
+        while !self.eof && self.curr_token.kind == TokenKind::As {
+            let as_token = self.curr_token.to_owned();
+            self.accept_it();
+            let Ok(ty) = Type::try_from_token(&self.curr_token) else {
+                panic!("{loc} error: expected type after `as`, but got: {string}",
+                       loc = as_token.loc,
+                       string = self.curr_token.string)
+            };
+            self.accept_it();
+            factor_ast = Box::new(Expr::Cast(factor_ast, ty));
+        }
+
+        factor_ast
+    }
+
+    // factor ::= ! factor | ~ factor | - factor | + factor | * factor | & factor | postfix(( expr )) | [ expr (, expr)* ]
+    //          | integer | float | string | char | bool | null | literal { (literal : expr ,)* } | literal . literal (( expr (, expr)* ))?
+    //          | builtin ( (expr (, expr)*)? ) | postfix(literal)
+    fn parse_factor(&mut self) -> Box::<Expr<'a>> {
+        if self.eof {
+            panic!("{loc} error: unexpected end of expression after: {string}",
+                   loc = self.curr_token.loc,
+                   string = self.curr_token.string)
+        }
 
         match self.curr_token.kind {
-            TokenKind::Plus => {
+            TokenKind::Bang => {
                 self.accept_it();
-                let expr_ast = self.parse_expr();
-                Box::new(Expr::Add(term_ast, expr_ast))
+                let factor_ast = self.parse_factor();
+                Box::new(Expr::Not(factor_ast))
             }
 
-            TokenKind::Minus => {
+            TokenKind::Tilde => {
                 self.accept_it();
-                let expr_ast = self.parse_expr();
-                Box::new(Expr::Sub(term_ast, expr_ast))
+                let factor_ast = self.parse_factor();
+                Box::new(Expr::BitNot(factor_ast))
             }
 
-            _ => term_ast,
-        }
-    }
+            TokenKind::Minus => {
+                self.accept_it();
+                if !self.eof && self.curr_token.kind == TokenKind::Int {
+                    return self.get_int(true)
+                }
+                let factor_ast = self.parse_factor();
+                Box::new(Expr::Neg(factor_ast))
+            }
 
-    // term ::= factor (* term | / term | epsilon)
-    fn parse_term(&mut self) -> Box::<Expr<'a>> {
-        let factor_ast = self.parse_factor();
+            // `+x` is just `x`, so there's nothing to check later, but the type of the operand
+            TokenKind::Plus => {
+                let loc = self.curr_token.loc.to_owned();
+                self.accept_it();
+                let factor_ast = self.parse_factor();
+                match factor_ast.infer(self.sym_map) {
+                    Ok((ty, _)) if !ty.is_numeric() => panic!("{loc} error: `+` expects a numeric operand, but got: {ty}"),
+                    _ => factor_ast
+                }
+            }
 
-        match self.curr_token.kind {
             TokenKind::Asterisk => {
                 self.accept_it();
-                let term_ast = self.parse_term();
-                Box::new(Expr::Mul(factor_ast, term_ast))
+                let factor_ast = self.parse_factor();
+                Box::new(Expr::Deref(factor_ast))
             }
 
-            TokenKind::Slash => {
+            TokenKind::Ampersand => {
                 self.accept_it();
-                let term_ast = self.parse_term();
-                Box::new(Expr::Div(factor_ast, term_ast))
+                let factor_ast = self.parse_factor();
+                Box::new(Expr::AddrOf(factor_ast))
             }
 
-            _ => factor_ast,
-        }
-    }
-
-    // factor ::= ( expr ) | integer
-    fn parse_factor(&mut self) -> Box::<Expr<'a>> {
-        match self.curr_token.kind {
             TokenKind::LParen => {
                 self.accept_it();
                 let expr_ast = self.parse_expr();
                 self.accept(TokenKind::RParen);
-                expr_ast
+                self.parse_postfix(expr_ast)
             }
 
-            TokenKind::Int => Box::new(Expr::I64(self.get_int())),
-            TokenKind::Flt => Box::new(Expr::F64(self.get_flt())),
-
-            TokenKind::Lit => if let Some(ref sym) = self.sym_map.get(self.curr_token.string) {
+            TokenKind::LBracket => {
                 self.accept_it();
-                match &sym.kind {
-                    AstKind::VarDecl(vd) => vd.value.to_owned(),
-                    _ => todo!()
+                let mut items = Vec::new();
+                while !self.eof && self.curr_token.kind != TokenKind::RBracket {
+                    items.push(self.parse_expr());
+                    if !self.eof && self.curr_token.kind == TokenKind::Comma {
+                        self.accept_it();
+                    } else {
+                        break
+                    }
+                }
+                if self.eof || self.curr_token.kind != TokenKind::RBracket {
+                    panic!("{loc} error: expected `]` to close the array literal, but got: {string}",
+                           loc = self.curr_token.loc,
+                           string = self.curr_token.string)
                 }
+                self.accept_it();
+                Box::new(Expr::Array(items))
+            }
+
+            TokenKind::Int => self.get_int(false),
+            TokenKind::Flt => self.get_flt(),
+            TokenKind::Str => {
+                let bytes = Lexer::unescape(self.curr_token.string, &self.curr_token.loc);
+                self.accept_it();
+                Box::new(Expr::Str(bytes))
+            }
+
+            TokenKind::Char => {
+                let bytes = Lexer::unescape(self.curr_token.string, &self.curr_token.loc);
+                self.accept_it();
+                Box::new(Expr::Char(bytes[0]))
+            }
+
+            TokenKind::Bool => {
+                let bval = self.curr_token.string == "true";
+                self.accept_it();
+                Box::new(Expr::Bool(bval))
+            }
+
+            TokenKind::Null => {
+                self.accept_it();
+                Box::new(Expr::Null)
+            }
+
+            TokenKind::Lit => if let Some(sym) = self.sym_map.get(self.curr_token.string) {
+                let lit = self.curr_token.to_owned();
+                self.accept_it();
+                let expr = match &sym.kind {
+                    SymKind::Const(value) => value.to_owned(),
+                    SymKind::Type if !self.eof && self.curr_token.kind == TokenKind::LCurly => return self.parse_struct(lit),
+                    SymKind::Type if !self.eof && self.curr_token.kind == TokenKind::Dot && matches!(sym.ty, Type::Enum(..)) => {
+                        return self.parse_variant(lit)
+                    }
+                    _ => Box::new(Expr::Lit(lit))
+                };
+                self.parse_postfix(expr)
+            } else if (BUILTINS.contains(&self.curr_token.string) || self.fns.contains_key(self.curr_token.string)) &&
+                matches!(self.tokens.get(self.curr_idx), Some(t) if t.kind == TokenKind::LParen)
+            {
+                let call = self.parse_call();
+                self.parse_postfix(call)
             } else {
                 panic!("{loc} error: undefined symbol: {string}",
                        loc = self.curr_token.loc,
@@ -124,24 +266,300 @@ impl<'a> ExprParser<'a> {
         }
     }
 
-    // integer ::= ... -2 | -1 | 0 | 1 | 2 ...
-    fn get_int(&mut self) -> i64 {
+    // postfix(e) ::= e ([ expr ] | . literal)*
+    fn parse_postfix(&mut self, mut expr: Box::<Expr<'a>>) -> Box::<Expr<'a>> {
+        while !self.eof {
+            match self.curr_token.kind {
+                TokenKind::LBracket => {
+                    let loc = self.curr_token.loc.to_owned();
+                    self.accept_it();
+                    let index = self.parse_expr();
+                    if self.eof || self.curr_token.kind != TokenKind::RBracket {
+                        panic!("{loc} error: expected `]` after the index, but got: {string}",
+                               loc = self.curr_token.loc,
+                               string = self.curr_token.string)
+                    }
+                    self.accept_it();
+                    expr = Box::new(Expr::Index(expr, index, loc));
+                }
+                TokenKind::Dot => {
+                    let dot_token = self.curr_token.to_owned();
+                    if self.accept_it() || self.curr_token.kind != TokenKind::Lit {
+                        panic!("{loc} error: expected name of the field after `.`, but got: {string}",
+                               loc = dot_token.loc,
+                               string = self.curr_token.string)
+                    }
+                    let field_token = self.curr_token.to_owned();
+                    self.accept_it();
+                    expr = Box::new(Expr::Field(expr, field_token));
+                }
+                _ => break
+            }
+        }
+        expr
+    }
+
+    // struct ::= literal { (literal : expr ,)* }, the trailing comma being optional
+    fn parse_struct(&mut self, name_token: Box::<Token<'a>>) -> Box::<Expr<'a>> {
+        self.accept_it();
+        let mut fields = Vec::new();
+        while !self.eof && self.curr_token.kind != TokenKind::RCurly {
+            if self.curr_token.kind != TokenKind::Lit {
+                panic!("{loc} error: expected name of the field, but got: {string}",
+                       loc = self.curr_token.loc,
+                       string = self.curr_token.string)
+            }
+            let field_token = self.curr_token.to_owned();
+            if self.accept_it() || self.curr_token.kind != TokenKind::Colon {
+                panic!("{loc} error: expected `:` after the name of the field, but got: {string}",
+                       loc = self.curr_token.loc,
+                       string = self.curr_token.string)
+            }
+            self.accept_it();
+            fields.push((field_token, self.parse_expr()));
+            if !self.eof && self.curr_token.kind == TokenKind::Comma {
+                self.accept_it();
+            } else {
+                break
+            }
+        }
+        if self.eof || self.curr_token.kind != TokenKind::RCurly {
+            panic!("{loc} error: expected `}}` to close the literal of `{name}`, but got: {string}",
+                   loc = self.curr_token.loc,
+                   name = name_token.string,
+                   string = self.curr_token.string)
+        }
+        self.accept_it();
+        Box::new(Expr::Struct(name_token, fields))
+    }
+
+    // variant ::= literal . literal (( expr (, expr)* ))?
+    fn parse_variant(&mut self, name_token: Box::<Token<'a>>) -> Box::<Expr<'a>> {
+        let dot_token = self.curr_token.to_owned();
+        if self.accept_it() || self.curr_token.kind != TokenKind::Lit {
+            panic!("{loc} error: expected name of the variant after `.`, but got: {string}",
+                   loc = dot_token.loc,
+                   string = self.curr_token.string)
+        }
+        let variant_token = self.curr_token.to_owned();
+        let mut args = Vec::new();
+        if !self.accept_it() && self.curr_token.kind == TokenKind::LParen {
+            self.accept_it();
+            while !self.eof && self.curr_token.kind != TokenKind::RParen {
+                args.push(self.parse_expr());
+                if !self.eof && self.curr_token.kind == TokenKind::Comma {
+                    self.accept_it();
+                } else {
+                    break
+                }
+            }
+            if self.eof || self.curr_token.kind != TokenKind::RParen {
+                panic!("{loc} error: expected `)` to close the payload of `{name}.{vname}`, but got: {string}",
+                       loc = self.curr_token.loc,
+                       name = name_token.string,
+                       vname = variant_token.string,
+                       string = self.curr_token.string)
+            }
+            self.accept_it();
+        }
+        Box::new(Expr::Variant(name_token, variant_token, args))
+    }
+
+    // call ::= literal ( (expr (, expr)*)? )
+    fn parse_call(&mut self) -> Box::<Expr<'a>> {
+        let name_token = self.curr_token.to_owned();
+        self.accept_it();
+        let mut args = Vec::new();
+        if !self.accept_it() {
+            while !self.eof && self.curr_token.kind != TokenKind::RParen {
+                let loc = self.curr_token.loc.to_owned();
+                args.push((self.parse_expr(), loc));
+                if !self.eof && self.curr_token.kind == TokenKind::Comma {
+                    self.accept_it();
+                } else {
+                    break
+                }
+            }
+        }
+        if self.eof || self.curr_token.kind != TokenKind::RParen {
+            panic!("{loc} error: expected `)` to close the call to `{name}`, but got: {string}",
+                   loc = self.curr_token.loc,
+                   name = name_token.string,
+                   string = self.curr_token.string)
+        }
+        self.accept_it();
+
+        // builtins check their arguments themselves
+        let Some(sig) = self.fns.get(name_token.string) else {
+            return Box::new(Expr::Call(name_token, args.into_iter().map(|(arg, _)| arg).collect()))
+        };
+        let Some(ret_ty) = sig.ret_ty.to_owned() else {
+            panic!("{loc} error: `{name}` doesn't return a value\n{def_loc} note: `{name}` is defined here",
+                   loc = name_token.loc, name = name_token.string, def_loc = sig.name_token.loc)
+        };
+        let args = sig.check_args(args, &name_token.loc, self.sym_map);
+        Box::new(Expr::UserCall(name_token, args, ret_ty))
+    }
+
+    // integer ::= -? (0 | 1 | 2 ...) suffix?
+    fn get_int(&mut self, negative: bool) -> Box::<Expr<'a>> {
         if self.curr_token.kind == TokenKind::Int {
-            let ret = self.curr_token.string.parse().unwrap();
+            // the lexer has already made sure the literal is well-formed,
+            // but only the sign tells whether the magnitude is in range
+            let NumLit::Int(mut ival, suffix) = Lexer::scan_number(self.curr_token.string, &self.curr_token.loc) else {
+                unreachable!()
+            };
+            if negative { ival = -ival }
+            let ty = suffix.to_owned().unwrap_or(Type::of_int_lit(ival));
+            if !ty.fits(ival) {
+                panic!("{loc} error: literal out of range for {ty}: {sign}{string}",
+                       loc = self.curr_token.loc,
+                       sign = if negative { "-" } else { "" },
+                       string = self.curr_token.string)
+            }
+            let ret = if suffix.is_none() {
+                Expr::I64(ival)
+            } else {
+                Expr::Int(ty.wrap(ival), ty)
+            };
             self.accept_it();
-            ret
+            Box::new(ret)
         } else {
             panic!("token is not an int")
         }
     }
 
-    fn get_flt(&mut self) -> f64 {
+    fn get_flt(&mut self) -> Box::<Expr<'a>> {
         if self.curr_token.kind == TokenKind::Flt {
-            let ret = self.curr_token.string.parse().unwrap();
+            let ret = match Lexer::scan_number(self.curr_token.string, &self.curr_token.loc) {
+                NumLit::Flt(flt, Some(Type::F32)) => Expr::Flt(flt as f32 as _, Type::F32),
+                NumLit::Flt(flt, Some(ty)) => Expr::Flt(flt, ty),
+                NumLit::Flt(flt, None) => Expr::F64(flt),
+                NumLit::Int(..) => unreachable!()
+            };
             self.accept_it();
-            ret
+            Box::new(ret)
         } else {
             panic!("token is not a float")
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{parser::Sym, FnMap};
+
+    /// Operators of `ExprParser::binary_op`, a row per precedence level, from the loosest to the tightest.
+    const ROWS: &[&[(&str, &str)]] = &[
+        &[("||", "Or")],
+        &[("&&", "And")],
+        &[("|", "BitOr")],
+        &[("^", "BitXor")],
+        &[("&", "BitAnd")],
+        &[("==", "Eq"), ("!=", "Ne")],
+        &[("<", "Lt"), ("<=", "Le"), (">", "Gt"), (">=", "Ge")],
+        &[("<<", "Shl"), (">>", "Shr")],
+        &[("+", "Add"), ("-", "Sub")],
+        &[("*", "Mul"), ("/", "Div"), ("%", "Rem")],
+    ];
+
+    /// Parses `src` with locals of the given names and types in scope, handing the tree to `f`.
+    pub(crate) fn with_expr<R>(src: &str, vars: &[(&'static str, Type)], f: impl FnOnce(&Expr, &SymMap) -> R) -> R {
+        let content = src.to_owned();
+        let mut lexer = Lexer::new("test.w", Ok(&content)).unwrap();
+        lexer.lex();
+        let sym_map = vars.iter().map(|(name, ty)| {
+            (*name, Sym { ty: ty.to_owned(), kind: SymKind::Local, il_name: name.to_string() })
+        }).collect::<SymMap>();
+        let fns = FnMap::new();
+        let expr = ExprParser::new(lexer.tokens.iter().collect(), &sym_map, &fns).parse();
+        f(&expr, &sym_map)
+    }
+
+    /// The tree as it's printed by `Debug`.
+    fn parse(src: &str) -> String {
+        with_expr(src, &[], |expr, _| format!("{expr:?}"))
+    }
+
+    fn eval(src: &str) -> i128 {
+        with_expr(src, &[], |expr, sym_map| expr.eval_int(sym_map))
+    }
+
+    #[test]
+    fn subtraction_and_division_are_left_associative() {
+        assert_eq!(eval("10 - 3 - 2"), 5);
+        assert_eq!(eval("8 / 4 / 2"), 1);
+        assert_eq!(eval("20 % 7 % 4"), 2);
+        assert_eq!(eval("1 - 2 + 3"), 2);
+        assert_eq!(eval("12 / 3 * 2"), 8);
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        assert_eq!(eval("2 + 3 * 4"), 14);
+        assert_eq!(eval("2 * 3 + 4"), 10);
+        assert_eq!(eval("(2 + 3) * 4"), 20);
+        assert_eq!(eval("10 - 6 / 2"), 7);
+    }
+
+    #[test]
+    fn unary_minus_and_plus() {
+        assert_eq!(eval("-3"), -3);
+        assert_eq!(eval("-3 - -2"), -1);
+        assert_eq!(eval("2 * -3"), -6);
+        assert_eq!(eval("-(2 + 3) * 2"), -10);
+        assert_eq!(eval("- -4"), 4);
+        assert_eq!(eval("+5 - +2"), 3);
+        assert_eq!(eval("-9223372036854775808"), i64::MIN as i128);
+    }
+
+    #[test]
+    fn unary_plus_is_the_operand_itself() {
+        assert_eq!(parse("+5"), "I64(5)");
+        assert_eq!(parse("+(1 + 2)"), "Add(I64(1), I64(2))");
+    }
+
+    #[test]
+    #[should_panic(expected = "`+` expects a numeric operand, but got: bool")]
+    fn unary_plus_on_a_bool() {
+        parse("+true");
+    }
+
+    #[test]
+    fn every_operator_is_left_associative() {
+        for &(op, name) in ROWS.iter().flat_map(|row| row.iter()) {
+            assert_eq!(parse(&format!("1 {op} 2 {op} 3")), format!("{name}({name}(I64(1), I64(2)), I64(3))"), "`{op}`");
+        }
+    }
+
+    #[test]
+    fn operators_of_a_row_associate_left_with_each_other() {
+        for row in ROWS.iter() {
+            for &(a, aname) in row.iter() {
+                for &(b, bname) in row.iter() {
+                    assert_eq!(parse(&format!("1 {a} 2 {b} 3")), format!("{bname}({aname}(I64(1), I64(2)), I64(3))"), "`{a}` and `{b}`");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_row_binds_tighter_than_the_ones_above_it() {
+        for (i, lo_row) in ROWS.iter().enumerate() {
+            for hi_row in ROWS[i + 1..].iter() {
+                for &(lo, lo_name) in lo_row.iter() {
+                    for &(hi, hi_name) in hi_row.iter() {
+                        assert_eq!(parse(&format!("1 {lo} 2 {hi} 3")), format!("{lo_name}(I64(1), {hi_name}(I64(2), I64(3)))"), "`{lo}` and `{hi}`");
+                        assert_eq!(parse(&format!("1 {hi} 2 {lo} 3")), format!("{lo_name}({hi_name}(I64(1), I64(2)), I64(3))"), "`{hi}` and `{lo}`");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn casts_bind_tighter_than_any_binary_operator() {
+        assert_eq!(parse("1 * 2 as u8"), "Mul(I64(1), Cast(I64(2), U8))");
+    }
+}
@@ -1,10 +1,12 @@
+use crate::ast::Type;
+
 use std::ptr;
 use std::str::Lines;
 use std::fmt::{Debug, Display};
 use std::iter::{Peekable, Enumerate};
 
 pub type Tokens<'a> = Vec::<Box::<Token<'a>>>;
-pub type TokensRefs<'a> = Vec::<&'a Box::<Token<'a>>>;
+pub type TokensRefs<'a, 'b> = Vec::<&'b Box::<Token<'a>>>;
 pub type IoResultRef<'a, T> = Result<T, &'a std::io::Error>;
 
 type LinesIterator<'a> = Peekable::<Enumerate::<Lines<'a>>>;
@@ -60,10 +62,26 @@ impl Debug for Loc {
 #[derive(PartialEq, Debug, Clone)]
 pub enum TokenKind {
     Fn,
+    As,
+    Struct,
+    Enum,
+    Match,
+    Return,
+    Const,
+    Let,
     Int,
     Flt,
     Lit,
+    Str,
+    Bool,
+    Char,
+    Null,
     Comma,
+    Colon,
+    Dot,
+    /// `_`, the pattern matching anything.
+    Underscore,
+    FatArrow,
     Type,
     Plus,
     Asterisk,
@@ -75,8 +93,31 @@ pub enum TokenKind {
     RParen,
     LCurly,
     RCurly,
+    LBracket,
+    RBracket,
     LAngleBracket,
     RAngleBracket,
+    LAngleBracketEqual,
+    RAngleBracketEqual,
+    DoubleEqual,
+    BangEqual,
+    Bang,
+    DoubleAmpersand,
+    DoublePipe,
+    DoubleLAngleBracket,
+    DoubleRAngleBracket,
+    Percent,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+}
+
+/// Value of a number literal, along with its type suffix, if there's one.
+#[derive(Debug, Clone)]
+pub enum NumLit {
+    Int(i128, Option::<Type>),
+    Flt(f64, Option::<Type>),
 }
 
 #[derive(Debug, Clone)]
@@ -118,34 +159,230 @@ impl<'a> Lexer<'a> {
         Ok(lexer)
     }
 
-    const SEPARATORS: &'static [char] = &[';', '=', '*', '/', '-', '+', '(', ')', '{', '}', ','];
+    const SEPARATORS: &'static [char] = &[';', ':', '=', '*', '/', '-', '+', '(', ')', '{', '}', '[', ']', ',', '<', '>', '!', '&', '|', '%', '^', '~'];
+
+    /// Operators made of two separators, these are kept as a single token.
+    const OPERATORS: &'static [&'static str] = &["==", "!=", "<=", ">=", "&&", "||", "<<", ">>", "=>"];
+
+    #[inline]
+    fn is_exponent_start(pending: &str) -> bool {
+        pending.starts_with(|c: char| c.is_ascii_digit()) &&
+            pending[..pending.len() - 1].bytes().all(|b| b.is_ascii_digit() || b == b'_' || b == b'.') &&
+            pending.ends_with(['e', 'E'])
+    }
 
     fn split_whitespace_preserve_indices(input: &str) -> Vec::<(usize, &str)> {
-        let (s, e, mut ret) = input.char_indices().fold((0, 0, Vec::with_capacity(input.len() / 2)),
-            |(s, e, mut ret), (i, c)|
-        {
-            let is_sepa = Self::SEPARATORS.contains(&c);
+        let mut ret = Vec::with_capacity(input.len() / 2);
+        let mut chars = input.char_indices();
+        let mut s = 0;
+        while let Some((i, c)) = chars.next() {
+            if c == '"' || c == '\'' {
+                let quote = c;
+                if s != i {
+                    ret.push((s, &input[s..i]))
+                }
+                // consume the whole literal, so separators and whitespace inside of it are preserved
+                let mut e = input.len();
+                let mut escaped = false;
+                for (j, c) in chars.by_ref() {
+                    match c {
+                        '\\' if !escaped => escaped = true,
+                        _ if c == quote && !escaped => { e = j + 1; break }
+                        _ => escaped = false
+                    }
+                }
+                ret.push((i, &input[i..e]));
+                s = e;
+                continue
+            }
+
+            // the sign of an exponent, e.g. in `1e-9`, belongs to the number
+            if (c == '-' || c == '+') && Self::is_exponent_start(&input[s..i]) {
+                continue
+            }
+
+            // a dot separates a field from the value, unless it's the decimal point of a number
+            let is_sepa = Self::SEPARATORS.contains(&c) ||
+                c == '.' && !input[s..i].starts_with(|c: char| c.is_ascii_digit());
             if c.is_whitespace() || is_sepa {
                 if s != i {
                     ret.push((s, &input[s..i]))
                 }
                 if is_sepa {
-                    ret.push((s + i - s, &input[i..=i]))
+                    let len = Self::OPERATORS.iter()
+                        .find(|op| input[i..].starts_with(*op))
+                        .map_or(1, |op| op.len());
+                    if len > 1 { chars.next(); }
+                    ret.push((i, &input[i..i + len]));
+                    s = i + len;
+                } else {
+                    s = i + c.len_utf8();
                 }
-                (i + c.len_utf8(), e, ret)
-            } else {
-                (s, i + c.len_utf8(), ret)
             }
-        });
+        }
 
-        if s != e && !input[s..].is_empty() {
+        if s < input.len() {
             ret.push((s, &input[s..]))
         }
 
         ret
     }
 
+    /// Decodes a quoted literal (quotes included) into raw bytes, reporting malformed escapes at their exact column.
+    pub fn unescape(string: &str, loc: &Loc) -> Vec::<u8> {
+        let err_loc = |col: usize| Loc { col: loc.col + col, ..loc.to_owned() };
+
+        let bytes = string.as_bytes();
+        let (quote, end) = (bytes[0], bytes.len() - 1);
+        if end == 0 || bytes[end] != quote {
+            panic!("{loc} error: unterminated literal: {string}")
+        }
+
+        let mut ret = Vec::with_capacity(bytes.len());
+        let mut i = 1;
+        while i < end {
+            if bytes[i] != b'\\' {
+                ret.push(bytes[i]);
+                i += 1;
+                continue
+            }
+
+            // the closing quote itself is escaped
+            if i + 1 == end {
+                panic!("{loc} error: unterminated literal: {string}")
+            }
+
+            let c = bytes[i + 1];
+            let b = match c {
+                b'n'  => b'\n',
+                b't'  => b'\t',
+                b'r'  => b'\r',
+                b'0'  => b'\0',
+                b'\\' => b'\\',
+                b'"'  => b'"',
+                b'\'' => b'\'',
+                b'x'  => {
+                    let hex = bytes.get(i + 2..i + 4)
+                        .filter(|h| i + 4 <= end && h.iter().all(u8::is_ascii_hexdigit))
+                        .and_then(|h| std::str::from_utf8(h).ok())
+                        .and_then(|h| u8::from_str_radix(h, 16).ok())
+                        .unwrap_or_else(|| {
+                            panic!("{loc} error: expected two hex digits after `\\x`", loc = err_loc(i))
+                        });
+                    i += 2;
+                    hex
+                }
+                _ => panic!("{loc} error: unknown escape sequence: `\\{c}`",
+                            loc = err_loc(i), c = c as char)
+            };
+
+            ret.push(b);
+            i += 2;
+        }
+
+        ret
+    }
+
+    /// Scans a number literal: decimal, `0x` hex, `0o` octal and `0b` binary integers, decimal floats
+    /// with an optional exponent, `_` separators and a type suffix, reporting malformed literals at the
+    /// column of the offending character. Integers are unsigned here, see [`Lexer::token_kind`].
+    pub fn scan_number(string: &str, loc: &Loc) -> NumLit {
+        let err_loc = |col: usize| Loc { col: loc.col + col, ..loc.to_owned() };
+
+        let bytes = string.as_bytes();
+        let (radix, name, mut i) = match bytes {
+            [b'0', b'x' | b'X', ..] => (16, "hex", 2),
+            [b'0', b'o' | b'O', ..] => (8, "octal", 2),
+            [b'0', b'b' | b'B', ..] => (2, "binary", 2),
+            _ => (10, "decimal", 0),
+        };
+
+        let mut digits = String::with_capacity(bytes.len());
+        let scan_digits = |i: &mut usize, digits: &mut String| {
+            let start = *i;
+            while *i < bytes.len() && (bytes[*i] == b'_' || (bytes[*i] as char).is_digit(radix)) {
+                if bytes[*i] != b'_' { digits.push(bytes[*i] as _) }
+                *i += 1
+            }
+            bytes[start..*i].iter().any(|b| *b != b'_')
+        };
+
+        let has_digits = scan_digits(&mut i, &mut digits);
+        if i < bytes.len() && bytes[i].is_ascii_digit() {
+            panic!("{loc} error: invalid digit `{c}` in {name} literal: {string}",
+                   loc = err_loc(i), c = bytes[i] as char)
+        }
+        if !has_digits {
+            panic!("{loc} error: expected {name} digits in number literal: {string}", loc = err_loc(i))
+        }
+
+        let mut is_flt = false;
+        if radix == 10 && bytes.get(i) == Some(&b'.') {
+            is_flt = true;
+            digits.push('.');
+            i += 1;
+            scan_digits(&mut i, &mut digits);
+        }
+        if radix == 10 && matches!(bytes.get(i), Some(b'e' | b'E')) {
+            is_flt = true;
+            digits.push('e');
+            i += 1;
+            if let Some(sign @ (b'+' | b'-')) = bytes.get(i) {
+                digits.push(*sign as _);
+                i += 1
+            }
+            if !scan_digits(&mut i, &mut digits) {
+                panic!("{loc} error: expected digits in the exponent of number literal: {string}", loc = err_loc(i))
+            }
+        }
+
+        let suffix = &string[i..];
+        let ty = if suffix.is_empty() { None } else {
+            match Type::from_name(suffix) {
+                Some(ty) if ty.is_float() && radix != 10 => {
+                    panic!("{loc} error: {name} literal can't have a float suffix: {string}", loc = err_loc(i))
+                }
+                Some(ty) if ty.is_int() && is_flt => {
+                    panic!("{loc} error: float literal can't have an integer suffix: {string}", loc = err_loc(i))
+                }
+                Some(ty) if ty.is_numeric() => Some(ty),
+                _ => panic!("{loc} error: invalid suffix `{suffix}` for number literal: {string}", loc = err_loc(i))
+            }
+        };
+
+        if is_flt || ty.as_ref().is_some_and(Type::is_float) {
+            let fval = digits.parse::<f64>().unwrap();
+            let lit_ty = ty.to_owned().unwrap_or(Type::F64);
+            if fval.is_infinite() || lit_ty == Type::F32 && (fval as f32).is_infinite() {
+                panic!("{loc} error: literal out of range for {lit_ty}: {string}")
+            }
+            return NumLit::Flt(fval, ty)
+        }
+
+        // the magnitude of the most negative value is accepted, so that the parser can negate it,
+        // e.g. `-9223372036854775808` or `-128i8`, unsuffixed literals are range-checked against the type they take
+        let lit_ty = ty.to_owned().unwrap_or(Type::U64);
+        match u128::from_str_radix(&digits, radix).ok().and_then(|v| i128::try_from(v).ok()) {
+            Some(v) if lit_ty.fits(v) || lit_ty.fits(-v) => NumLit::Int(v, ty),
+            _ => panic!("{loc} error: literal out of range for {lit_ty}: {string}")
+        }
+    }
+
     fn token_kind(&self, string: &str, err_loc: &Loc) -> TokenKind {
+        match string {
+            "==" => return TokenKind::DoubleEqual,
+            "!=" => return TokenKind::BangEqual,
+            "<=" => return TokenKind::LAngleBracketEqual,
+            ">=" => return TokenKind::RAngleBracketEqual,
+            "&&" => return TokenKind::DoubleAmpersand,
+            "||" => return TokenKind::DoublePipe,
+            "<<" => return TokenKind::DoubleLAngleBracket,
+            ">>" => return TokenKind::DoubleRAngleBracket,
+            "=>" => return TokenKind::FatArrow,
+            "_"  => return TokenKind::Underscore,
+            _ => {}
+        }
+
         let first = string.as_bytes()[0];
         match first as _ {
             '+' => TokenKind::Plus,
@@ -157,21 +394,47 @@ impl<'a> Lexer<'a> {
             ')' => TokenKind::RParen,
             '{' => TokenKind::LCurly,
             '}' => TokenKind::RCurly,
+            '[' => TokenKind::LBracket,
+            ']' => TokenKind::RBracket,
             '>' => TokenKind::RAngleBracket,
             '<' => TokenKind::LAngleBracket,
+            '!' => TokenKind::Bang,
+            '%' => TokenKind::Percent,
+            '&' => TokenKind::Ampersand,
+            '|' => TokenKind::Pipe,
+            '^' => TokenKind::Caret,
+            '~' => TokenKind::Tilde,
             ',' => TokenKind::Comma,
             ';' => TokenKind::Semicolon,
-            '0'..='9' => if string.parse::<i64>().is_ok() {
-                TokenKind::Int
-            } else if string.parse::<f64>().is_ok() {
-                TokenKind::Flt
+            ':' => TokenKind::Colon,
+            '.' => TokenKind::Dot,
+            '"' => {
+                Self::unescape(string, err_loc);
+                TokenKind::Str
+            }
+            '\'' => if Self::unescape(string, err_loc).len() == 1 {
+                TokenKind::Char
             } else {
-                panic!("{err_loc} error: failed to parse number: {string}")
+                panic!("{err_loc} error: character literal must contain exactly one byte: {string}")
+            }
+            '0'..='9' => match Self::scan_number(string, err_loc) {
+                NumLit::Int(..) => TokenKind::Int,
+                NumLit::Flt(..) => TokenKind::Flt,
             }
             'a'..='z' | 'A'..='Z' => match string {
-                "i64" => TokenKind::Type,
-                "f64" => TokenKind::Type,
+                "i8" | "i16" | "i32" | "i64" |
+                "u8" | "u16" | "u32" | "u64" |
+                "f32" | "f64" | "str" | "bool" | "char" => TokenKind::Type,
+                "true" | "false" => TokenKind::Bool,
+                "null" => TokenKind::Null,
+                "as"  => TokenKind::As,
+                "const" => TokenKind::Const,
+                "let" => TokenKind::Let,
                 "fn"  => TokenKind::Fn,
+                "struct" => TokenKind::Struct,
+                "enum"  => TokenKind::Enum,
+                "match" => TokenKind::Match,
+                "return" => TokenKind::Return,
                 _ => TokenKind::Lit,
             }
             _ => panic!("{err_loc} error: unexpected token: {string}")
@@ -204,3 +467,118 @@ impl<'a> Lexer<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(string: &str) -> NumLit {
+        let loc = Loc { row: 0, col: 0, file_path: Box::new(FilePath::new("test.w")) };
+        Lexer::scan_number(string, &loc)
+    }
+
+    fn int(string: &str) -> (i128, Option::<Type>) {
+        match scan(string) {
+            NumLit::Int(v, ty) => (v, ty),
+            lit => panic!("expected an integer, but got: {lit:?}")
+        }
+    }
+
+    fn flt(string: &str) -> (f64, Option::<Type>) {
+        match scan(string) {
+            NumLit::Flt(v, ty) => (v, ty),
+            lit => panic!("expected a float, but got: {lit:?}")
+        }
+    }
+
+    fn kinds(src: &str) -> Vec::<TokenKind> {
+        let content = src.to_owned();
+        let mut lexer = Lexer::new("test.w", Ok(&content)).unwrap();
+        lexer.lex();
+        lexer.tokens.iter().map(|t| t.kind.to_owned()).collect()
+    }
+
+    #[test]
+    fn integers_in_every_radix() {
+        assert_eq!(int("42"), (42, None));
+        assert_eq!(int("0xff"), (255, None));
+        assert_eq!(int("0XfF"), (255, None));
+        assert_eq!(int("0o17"), (15, None));
+        assert_eq!(int("0b1010"), (10, None));
+        assert_eq!(int("1_000_000"), (1_000_000, None));
+        assert_eq!(int("0xdead_beef"), (0xdead_beef, None));
+    }
+
+    #[test]
+    fn integer_suffixes() {
+        assert_eq!(int("42i8"), (42, Some(Type::I8)));
+        assert_eq!(int("0xffu8"), (255, Some(Type::U8)));
+        assert_eq!(int("128i8"), (128, Some(Type::I8)), "the magnitude of `-128i8` is accepted");
+        assert_eq!(int("18446744073709551615"), (u64::MAX as i128, None));
+        assert_eq!(int("0b1_u16"), (1, Some(Type::U16)));
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(flt("1.5"), (1.5, None));
+        assert_eq!(flt("1e3"), (1000.0, None));
+        assert_eq!(flt("2.5e-3"), (0.0025, None));
+        assert_eq!(flt("1_0.2_5E+1"), (102.5, None));
+        assert_eq!(flt("1.5f32"), (1.5, Some(Type::F32)));
+        assert_eq!(flt("3f64"), (3.0, Some(Type::F64)));
+    }
+
+    #[test]
+    fn exponents_with_a_sign_stay_in_the_literal() {
+        assert_eq!(kinds("1e-3-2"), [TokenKind::Flt, TokenKind::Minus, TokenKind::Int]);
+        assert_eq!(kinds("x = 2E+10;"), [TokenKind::Lit, TokenKind::Equal, TokenKind::Flt, TokenKind::Semicolon]);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid digit `2` in binary literal")]
+    fn invalid_digit() {
+        scan("0b102");
+    }
+
+    #[test]
+    #[should_panic(expected = "expected hex digits")]
+    fn missing_digits() {
+        scan("0x");
+    }
+
+    #[test]
+    #[should_panic(expected = "expected digits in the exponent")]
+    fn missing_exponent() {
+        scan("1e+");
+    }
+
+    #[test]
+    #[should_panic(expected = "float literal can't have an integer suffix")]
+    fn float_with_integer_suffix() {
+        scan("1.5i32");
+    }
+
+    #[test]
+    #[should_panic(expected = "octal literal can't have a float suffix")]
+    fn octal_with_float_suffix() {
+        scan("0o7f32");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid suffix `abc`")]
+    fn invalid_suffix() {
+        scan("1abc");
+    }
+
+    #[test]
+    #[should_panic(expected = "literal out of range for u8: 256u8")]
+    fn suffixed_out_of_range() {
+        scan("256u8");
+    }
+
+    #[test]
+    #[should_panic(expected = "literal out of range for u64: 18446744073709551616")]
+    fn unsuffixed_out_of_range() {
+        scan("18446744073709551616");
+    }
+}
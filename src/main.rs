@@ -13,7 +13,15 @@ use compiler::*;
 fn main() -> IoResultRef::<'static, ()> {
     let argv = env::args().collect::<Vec::<_>>();
     if argv.len() < 2 {
-        panic!("usage: {program} <file_path>", program = argv[0]);
+        panic!("usage: {program} <file_path> [--no-bounds-checks]", program = argv[0]);
+    }
+
+    let mut bounds_checks = true;
+    for flag in argv[2..].iter() {
+        match flag.as_str() {
+            "--no-bounds-checks" => bounds_checks = false,
+            _ => panic!("error: unknown flag: `{flag}`")
+        }
     }
 
     let ref file_path = argv[1];
@@ -30,7 +38,7 @@ fn main() -> IoResultRef::<'static, ()> {
     let mut parser = Parser::new(&lexer.tokens);
     let asts = parser.parse();
 
-    let mut compiler = Compiler::new(file_path).unwrap();
+    let mut compiler = Compiler::new(file_path, bounds_checks).unwrap();
     compiler.compile(asts).unwrap();
 
     Ok(())
@@ -1,57 +1,97 @@
 use crate::{
     expr_parser::ExprParser,
-    lexer::{Loc, Token, TokenKind, Tokens},
-    ast::{Ast, AstKind, Asts, Expr, Fn, FnArg, FnCall, Type, VarDecl},
+    lexer::{Lexer, Loc, NumLit, Token, TokenKind, Tokens, TokensRefs},
+    ast::{Assign, Ast, AstKind, Asts, EnumDef, Expr, Fn, FnArg, FnCall, FnSig, Match, MatchArm, StructDef, Type, VarDecl},
 };
 
+use std::rc::Rc;
 use std::process::exit;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-pub type SymMap<'a> = HashMap::<&'a str, Box::<Ast<'a>>>;
+#[derive(Debug, Clone)]
+pub enum SymKind<'a> {
+    /// QBE temporary of the enclosing function.
+    Local,
+    /// Local, whose address is taken, so it lives in a stack slot `%{il_name}.addr`.
+    Stack,
+    /// `data` definition, loaded and stored through its address.
+    Global,
+    /// Folded value, that every use is replaced with.
+    Const(Box::<Expr<'a>>),
+    /// Name of a struct or an enum, the type of the symbol is the type itself.
+    Type,
+}
 
+/// What a name refers to, as seen from the place it's used at.
 #[derive(Debug, Clone)]
-pub enum Ctx<'a> {
-    Fn(SymMap<'a>),
-    Global(SymMap<'a>),
-    FnCall(SymMap<'a>),
+pub struct Sym<'a> {
+    pub ty: Type,
+    pub kind: SymKind<'a>,
+    /// Name of the QBE temporary or data, shadowing declarations get a unique suffix, e.g. `x.1`, and globals a `g.` prefix.
+    pub il_name: String,
 }
 
-impl<'a> Ctx<'a> {
-    #[inline(always)]
-    pub fn sym_map(&self) -> &SymMap<'a> {
-        match self {
-            Ctx::Fn(sm) | Ctx::FnCall(sm) | Ctx::Global(sm) => sm
-        }
-    }
+/// Functions provided by the compiler itself, calls to them are lowered directly.
+pub const BUILTINS: &[&str] = &[
+    "print", "println", "eprint", "eprintln",
+    "alloc", "free", "realloc", "syscall",
+    "open", "read", "write", "close", "read_line", "read_i64", "read_f64",
+];
 
-    #[inline(always)]
-    pub fn sym_map_mut(&mut self) -> &mut SymMap<'a> {
-        match self {
-            Ctx::Fn(sm) | Ctx::FnCall(sm) | Ctx::Global(sm) => sm
-        }
-    }
+/// Builtins taking any number of values to print, the rest are checked by [`Expr::check_builtin`].
+const PRINTS: &[&str] = &["print", "println", "eprint", "eprintln"];
 
-    #[inline(always)]
-    pub fn empty_fn() -> Ctx<'static> {
-        Ctx::Fn(SymMap::new())
-    }
+/// Function `_start` calls into, when it's not defined, top-level statements are compiled into it.
+pub const ENTRY: &str = "main";
 
-    #[inline(always)]
-    pub fn empty_fncall() -> Ctx<'static> {
-        Ctx::FnCall(SymMap::new())
-    }
+pub type SymMap<'a> = HashMap::<&'a str, Sym<'a>>;
 
+/// Signatures of all functions in the file, by name.
+pub type FnMap<'a> = HashMap::<&'a str, FnSig<'a>>;
+
+/// Symbols visible at some point of the program, every `Ast` is compiled in the one it was parsed in.
+#[derive(Debug, Clone, Default)]
+pub struct Ctx<'a> {
+    sym_map: SymMap<'a>
+}
+
+impl<'a> Ctx<'a> {
     #[inline(always)]
-    pub fn empty_global() -> Ctx<'static> {
-        Ctx::Global(SymMap::new())
+    pub fn sym_map(&self) -> &SymMap<'a> {
+        &self.sym_map
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScopeKind {
+    Global,
+    Fn,
+    Block,
+}
+
+#[derive(Debug, Clone)]
+struct Scope<'a> {
+    kind: ScopeKind,
+    sym_map: SymMap<'a>,
+    /// Shadow counts of the enclosing function, restored when a function scope is left.
+    outer_shadows: HashMap::<&'a str, usize>,
+}
+
 pub struct Parser<'a, 'b> {
     idx: usize,
     eof: bool,
     tokens: &'b Tokens<'a>,
-    ctx: Box::<Ctx<'a>>,
+    /// Lexical scopes from the outermost to the innermost one: global, then function, then blocks.
+    scopes: Vec::<Scope<'a>>,
+    /// Signatures of all functions in the file, see [`Parser::collect_fns`].
+    fns: FnMap<'a>,
+    /// How many times each name has been declared in the current function, to give shadowing declarations unique names.
+    shadows: HashMap::<&'a str, usize>,
+    /// Names, whose address is taken, along with the function it's taken in, `None` being the top level.
+    /// Locals of these names are kept in stack slots.
+    addressed: HashSet::<(Option::<&'a str>, &'a str)>,
+    /// Function being parsed, `None` at the top level.
+    curr_fn: Option::<&'a str>,
 }
 
 impl<'a, 'b> Parser<'a, 'b> {
@@ -61,8 +101,91 @@ impl<'a, 'b> Parser<'a, 'b> {
             tokens,
             idx: 0,
             eof: false,
-            ctx: Box::new(Ctx::Global(HashMap::new()))
+            scopes: vec![Scope { kind: ScopeKind::Global, sym_map: SymMap::new(), outer_shadows: HashMap::new() }],
+            shadows: HashMap::new(),
+            fns: HashMap::new(),
+            addressed: HashSet::new(),
+            curr_fn: None,
+        }
+    }
+
+    #[inline]
+    fn push_scope(&mut self, kind: ScopeKind) {
+        // temporaries are local to a function, so every function starts counting from scratch
+        let outer_shadows = if kind == ScopeKind::Fn { std::mem::take(&mut self.shadows) } else { HashMap::new() };
+        self.scopes.push(Scope { kind, sym_map: SymMap::new(), outer_shadows })
+    }
+
+    #[inline]
+    fn pop_scope(&mut self) {
+        if let Some(Scope { kind: ScopeKind::Fn, outer_shadows, .. }) = self.scopes.pop() {
+            self.shadows = outer_shadows
+        }
+    }
+
+    /// Declares `name` in the innermost scope, returning the symbol it refers to now.
+    fn declare(&mut self, name_token: &Token<'a>, ty: &Type, kind: SymKind<'a>) -> Sym<'a> {
+        let name = name_token.string;
+        if let SymKind::Global = kind {
+            // globals become `data $name`, that has to be unique in the whole file
+            if self.scopes[0].sym_map.contains_key(name) || self.fns.contains_key(name) {
+                panic!("{loc} error: `{name}` is already defined at the top level", loc = name_token.loc)
+            }
+        }
+
+        let kind = match kind {
+            // aggregates live in memory already
+            SymKind::Local if self.addressed.contains(&(self.curr_fn, name)) && !ty.is_aggregate() && *ty != Type::Str => SymKind::Stack,
+            _ => kind
+        };
+
+        let count = self.shadows.entry(name).or_default();
+        let il_name = match kind {
+            // `data` shares the namespace with functions, the runtime, and string literals, so it gets a prefix no identifier has
+            SymKind::Global => format!("g.{name}"),
+            _ if *count == 0 => name.to_owned(),
+            _ => format!("{name}.{count}")
+        };
+        *count += 1;
+
+        let sym = Sym { ty: ty.to_owned(), kind, il_name };
+        self.scopes.last_mut().unwrap().sym_map.insert(name, sym.to_owned());
+        sym
+    }
+
+    /// Collects the tokens of an expression up to the terminating semicolon, consuming it.
+    fn parse_expr_tokens(&mut self, loc: &Loc, what: &str) -> TokensRefs<'a, 'b> {
+        let mut expr_tokens = Vec::new();
+        while self.tokens[self.idx].kind != TokenKind::Semicolon && !self.eof {
+            expr_tokens.push(&self.tokens[self.idx]);
+            self.advance();
+        }
+
+        self.type_check_token(|t| {
+            matches!(t.kind, TokenKind::Semicolon)
+        }, |string, loc| {
+            panic!("{loc} error: expected semicolon after {what}, but got: {string}")
+        });
+
+        self.advance();
+
+        if expr_tokens.is_empty() {
+            panic!("{loc} error: expected an expression in {what}")
         }
+
+        expr_tokens
+    }
+
+    /// Flattens the scope stack, so inner declarations shadow the outer ones.
+    fn visible(&self) -> SymMap<'a> {
+        self.scopes.iter().flat_map(|scope| scope.sym_map.iter()).map(|(name, sym)| {
+            (*name, sym.to_owned())
+        }).collect()
+    }
+
+    #[inline(always)]
+    fn ctx(&self) -> Box::<Ctx<'a>> {
+        Box::new(Ctx { sym_map: self.visible() })
     }
 
     #[inline]
@@ -98,55 +221,219 @@ impl<'a, 'b> Parser<'a, 'b> {
         }
     }
 
-    fn parse_decl(&mut self) -> VarDecl<'a> {
+    /// The struct or enum called `name`.
+    #[inline]
+    fn user_type(&self, name: &str) -> Option::<Type> {
+        self.scopes.iter().rev().find_map(|scope| scope.sym_map.get(name))
+            .filter(|sym| matches!(sym.kind, SymKind::Type))
+            .map(|sym| sym.ty.to_owned())
+    }
+
+    fn parse_type(&mut self) -> Type {
         let ref ty_token = self.tokens[self.idx];
 
         self.advance();
 
-        let ty = Type::try_from_token(&ty_token).unwrap();
-        if self.tokens[self.idx].kind == TokenKind::Asterisk {
-            todo!();
-            // ty = ty.to_ptr();
-            // self.advance();
+        let Some(ty) = Type::try_from_token(&ty_token).ok().or_else(|| self.user_type(ty_token.string)) else {
+            panic!("{loc} error: expected type, but got: {string}",
+                   loc = ty_token.loc, string = ty_token.string)
+        };
+        let mut ty = ty;
+        while self.tokens[self.idx].kind == TokenKind::Asterisk {
+            if ty == Type::Str {
+                panic!("{loc} error: pointers to str aren't supported", loc = ty_token.loc)
+            }
+            ty = Type::Ptr(Box::new(ty));
+            self.advance();
         }
 
+        if self.tokens[self.idx].kind != TokenKind::LBracket { return ty }
+
+        self.advance();
+
+        let ref len_token = self.tokens[self.idx];
+        let len = match len_token.kind {
+            TokenKind::Int => match Lexer::scan_number(len_token.string, &len_token.loc) {
+                NumLit::Int(len, None) if len > 0 => Some(len as usize),
+                _ => None
+            }
+            _ => None
+        };
+        let Some(len) = len else {
+            panic!("{loc} error: expected length of the array, but got: {string}",
+                   loc = len_token.loc, string = len_token.string)
+        };
+
+        self.advance();
+
+        self.type_check_token(|t| {
+            matches!(t.kind, TokenKind::RBracket)
+        }, |string, loc| {
+            panic!("{loc} error: expected `]` after the length of the array, but got: {string}")
+        });
+
+        self.advance();
+
+        if self.tokens[self.idx].kind == TokenKind::LBracket {
+            panic!("{loc} error: arrays of arrays aren't supported", loc = ty_token.loc)
+        }
+        if !ty.is_elem() {
+            panic!("{loc} error: arrays of {ty} aren't supported", loc = ty_token.loc)
+        }
+
+        Type::Array(Box::new(ty), len)
+    }
+
+    /// Parses `T x = ...;` or `let x = ...;`, where the type is inferred from the initializer, unless it's given as `let x: T`.
+    /// Arrays and structs may be declared without an initializer, `T[N] xs;`, then they're zeroed.
+    fn parse_decl(&mut self, constant: bool) -> VarDecl<'a> {
+        let ref ty_token = self.tokens[self.idx];
+
+        let inferred = ty_token.kind == TokenKind::Let;
+        let ty = if inferred {
+            self.advance();
+            None
+        } else {
+            Some(self.parse_type())
+        };
+
         let name_token = self.type_check_token_owned(|t| {
             matches!(t.kind, TokenKind::Lit)
         }, |string, loc| {
-            panic!("{loc} error: expected literal after the type, but got: {string}")
+            panic!("{loc} error: expected name of the variable, but got: {string}")
         });
 
-        self.advance();
         self.advance();
 
-        let mut expr_tokens = Vec::new();
-        while self.tokens[self.idx].kind != TokenKind::Semicolon && !self.eof {
-            expr_tokens.push(&self.tokens[self.idx]);
+        let ty = if inferred && self.tokens[self.idx].kind == TokenKind::Colon {
             self.advance();
+            Some(self.parse_type())
+        } else {
+            ty
+        };
+
+        if let (Some(ty), TokenKind::Semicolon) = (ty.as_ref().filter(|ty| ty.is_aggregate()), &self.tokens[self.idx].kind) {
+            self.advance();
+            let ty = ty.to_owned();
+            let global = self.scopes.len() == 1;
+            if constant {
+                panic!("{loc} error: constant `{name}` must have an initializer", loc = ty_token.loc, name = name_token.string)
+            }
+            let kind = if global { SymKind::Global } else { SymKind::Local };
+            let il_name = self.declare(&name_token, &ty, kind).il_name;
+            return VarDecl { ty, global, stack: false, il_name, value: None }
         }
 
         self.type_check_token(|t| {
-            matches!(t.kind, TokenKind::Semicolon)
+            matches!(t.kind, TokenKind::Equal)
         }, |string, loc| {
-            panic!("{loc} error: expected semicolon after decl, but got: {string}")
+            panic!("{loc} error: expected `=` after the name, but got: {string}")
         });
 
         self.advance();
 
-        let expr = ExprParser::new(expr_tokens, self.ctx.sym_map()).parse();
-        let value = match ty {
-            Type::I64 => Box::new(Expr::I64(expr.eval_int(self.ctx.sym_map()))),
-            Type::F64 => Box::new(Expr::F64(expr.eval_flt(self.ctx.sym_map()))),
+        let expr_tokens = self.parse_expr_tokens(&ty_token.loc, "decl");
+
+        let ref sym_map = self.visible();
+        let expr = ExprParser::new(expr_tokens, sym_map, &self.fns).parse();
+        let ty = ty.unwrap_or_else(|| {
+            let (ty, flex) = expr.infer(sym_map).unwrap_or_else(|err| panic!("{loc} error: {err}", loc = ty_token.loc));
+            // `null` and `alloc(n)` are `u8*`s only until they meet the pointer they initialize
+            if flex && ty.is_ptr() {
+                let (what, init) = match *expr {
+                    Expr::Null => ("`null`", "null"),
+                    _ => ("the pointer", "..."),
+                };
+                panic!("{loc} error: can't infer the type of {what}, annotate it: `let {name}: T* = {init};`",
+                       loc = ty_token.loc, name = name_token.string)
+            }
+            ty
+        });
+        expr.check_assign(&ty, sym_map).unwrap_or_else(|err| {
+            panic!("{loc} error: {err}", loc = ty_token.loc)
+        });
+
+        let value = expr.fold(&ty, sym_map, &ty_token.loc);
+        if constant && ty.is_aggregate() {
+            panic!("{loc} error: arrays and structs can't be constants, declare `{name}` as a global instead",
+                   loc = ty_token.loc, name = name_token.string)
+        }
+
+        let global = self.scopes.len() == 1;
+        // in a script, the initializer runs at the start of the implicit `main`
+        let script = !self.fns.contains_key(ENTRY);
+        if (constant || global && !script) && !value.is_const() {
+            panic!("{loc} error: initializer of {what} `{name}` must be a constant expression",
+                   loc = ty_token.loc, name = name_token.string,
+                   what = if constant { "constant" } else { "global" })
+        }
+
+        let kind = if constant {
+            SymKind::Const(value.to_owned())
+        } else if global {
+            SymKind::Global
+        } else {
+            SymKind::Local
         };
 
+        // declared only after the initializer, so it still sees a shadowed variable
+        let sym = self.declare(&name_token, &ty, kind);
+
         VarDecl {
-            name_token,
-            value,
+            ty,
+            global,
+            stack: matches!(sym.kind, SymKind::Stack),
+            il_name: sym.il_name,
+            value: Some(value),
+        }
+    }
+
+    /// Parses `x = ...;`, `xs[i] = ...;`, `*p = ...;` or `p.x = ...;`.
+    fn parse_assign(&mut self) -> Assign<'a> {
+        let ref first_token = self.tokens[self.idx];
+
+        let mut target_tokens = Vec::new();
+        while self.tokens[self.idx].kind != TokenKind::Equal && !self.eof {
+            target_tokens.push(&self.tokens[self.idx]);
+            self.advance();
+        }
+
+        self.advance();
+
+        let ref loc = first_token.loc;
+        let expr_tokens = self.parse_expr_tokens(loc, "assignment");
+
+        let ref sym_map = self.visible();
+        if let Some(Sym { kind: SymKind::Const(..), .. }) = sym_map.get(first_token.string) {
+            panic!("{loc} error: can't assign to constant `{name}`", name = first_token.string)
+        }
+
+        let target = ExprParser::new(target_tokens, sym_map, &self.fns).parse();
+        let ty = target.type_check(sym_map, loc);
+        if !target.is_place() {
+            panic!("{loc} error: only variables, elements of arrays, fields and dereferenced pointers can be assigned to")
         }
+        if let Type::Array(..) = ty {
+            panic!("{loc} error: arrays can't be assigned as a whole, assign to their elements instead")
+        }
+
+        let expr = ExprParser::new(expr_tokens, sym_map, &self.fns).parse();
+        expr.check_assign(&ty, sym_map).unwrap_or_else(|err| {
+            panic!("{loc} error: {err}")
+        });
+
+        let value = expr.fold(&ty, sym_map, loc);
+
+        Assign { target, value }
     }
 
     fn parse_fn_call(&mut self) -> FnCall<'a> {
         let name_token = self.tokens[self.idx].to_owned();
+        if !BUILTINS.contains(&name_token.string) && !self.fns.contains_key(name_token.string) {
+            panic!("{loc} error: call to undefined function: {name}",
+                   loc = name_token.loc, name = name_token.string)
+        }
+
         self.advance();
 
         if !matches! {
@@ -159,25 +446,36 @@ impl<'a, 'b> Parser<'a, 'b> {
 
         self.advance();
 
+        // builtins check their arguments themselves
+        let sig = self.fns.get(name_token.string).cloned();
+
         let mut args = Vec::new();
         while self.idx < self.tokens.len() && !self.eof {
             let ref t = self.tokens[self.idx];
             if t.kind == TokenKind::RParen { break }
 
+            let mut depth = 0;
             let mut expr_tokens = Vec::new();
-            while !matches!(self.tokens[self.idx].kind, TokenKind::Comma | TokenKind::RParen) && !self.eof {
+            while !self.eof {
+                match self.tokens[self.idx].kind {
+                    TokenKind::Comma | TokenKind::RParen if depth == 0 => break,
+                    TokenKind::LParen | TokenKind::LBracket | TokenKind::LCurly => depth += 1,
+                    TokenKind::RParen | TokenKind::RBracket | TokenKind::RCurly => depth -= 1,
+                    _ => {}
+                }
                 expr_tokens.push(&self.tokens[self.idx]);
                 self.advance();
             }
 
-            let expr = ExprParser::new(expr_tokens, self.ctx.sym_map()).parse();
-            let value = match t.kind {
-                TokenKind::RParen => break,
-                TokenKind::Int => Box::new(Expr::I64(expr.eval_int(self.ctx.sym_map()))),
-                TokenKind::Flt => Box::new(Expr::F64(expr.eval_flt(self.ctx.sym_map()))),
-                TokenKind::Lit => Box::new(Expr::Lit(t.to_owned())),
-                _ => panic!("{loc} expected int or float bruv, but got: {got}",
-                            loc = t.loc, got = t.string)
+            let ref sym_map = self.visible();
+            let expr = ExprParser::new(expr_tokens, sym_map, &self.fns).parse();
+            let value = if sig.is_some() { expr } else {
+                let ty = expr.type_check(sym_map, &t.loc);
+                if ty.is_aggregate() && PRINTS.contains(&name_token.string) {
+                    panic!("{loc} error: `{name}` can't print a value of type {ty}, print its elements instead",
+                           loc = t.loc, name = name_token.string)
+                }
+                expr.fold(&ty, sym_map, &t.loc)
             };
 
             if !matches! {
@@ -192,7 +490,7 @@ impl<'a, 'b> Parser<'a, 'b> {
                 self.advance();
             }
 
-            args.push(value);
+            args.push((value, t.loc.to_owned()));
         }
 
         if !matches! {
@@ -202,11 +500,25 @@ impl<'a, 'b> Parser<'a, 'b> {
             panic!("{loc} rparen was not met bruv", loc = name_token.loc);
         }
 
+        // the result, if there's one, is just dropped
+        let args = match sig {
+            Some(sig) => sig.check_args(args, &name_token.loc, &self.visible()),
+            None => {
+                let args = args.into_iter().map(|(arg, _)| arg).collect::<Vec::<_>>();
+                if !PRINTS.contains(&name_token.string) {
+                    Expr::check_builtin(name_token.string, &args, &self.visible()).unwrap_or_else(|err| {
+                        panic!("{loc} error: {err}", loc = name_token.loc)
+                    });
+                }
+                args
+            }
+        };
+
         self.advance();
         FnCall {args, name_token}
     }
 
-    fn parse_fn(&mut self) -> Fn<'a> {
+    fn parse_fn_sig(&mut self) -> FnSig<'a> {
         self.advance();
 
         let name_token = self.type_check_token_owned(|t| {
@@ -233,12 +545,11 @@ impl<'a, 'b> Parser<'a, 'b> {
 
             if t.kind == TokenKind::RParen { break }
 
-            let Ok(ty) = Type::try_from_token(t) else {
-                panic!("{loc} expected type but got: {string}",
-                       loc = t.loc, string = t.string)
-            };
+            let ty = self.parse_type();
+            if let Type::Array(..) = ty {
+                panic!("{loc} error: arrays can't be passed by value, pass a pointer to their element instead", loc = t.loc)
+            }
 
-            self.advance();
             let name_token = self.type_check_token_owned(|t| {
                 matches!(t.kind, TokenKind::Lit)
             }, |string, loc| {
@@ -246,8 +557,22 @@ impl<'a, 'b> Parser<'a, 'b> {
             });
 
             self.advance();
-            let arg = FnArg { ty, name_token };
+
+            if args.iter().any(|arg: &FnArg| arg.name_token.string == name_token.string) {
+                panic!("{loc} error: duplicate parameter: {name}",
+                       loc = name_token.loc, name = name_token.string)
+            }
+
+            let arg = FnArg { ty, stack: false, name_token };
             args.push(arg);
+
+            match self.tokens.get(self.idx) {
+                Some(t) if t.kind == TokenKind::Comma => self.advance(),
+                Some(t) if t.kind == TokenKind::RParen => {}
+                Some(t) => panic!("{loc} error: expected comma or `)` after a parameter, but got: {string}",
+                                  loc = t.loc, string = t.string),
+                None => break
+            }
         }
 
         if !matches! {
@@ -278,16 +603,22 @@ impl<'a, 'b> Parser<'a, 'b> {
 
             self.advance();
 
-            let Ok(ty) = Type::try_from_token(&self.tokens[self.idx]) else {
-                panic!("{loc} error: Expected arrow after rparen, but got: {string}",
-                    loc = self.tokens[self.idx].loc,
-                    string = self.tokens[self.idx].string)
-            };
-
-            self.advance();
+            let ref ty_token = self.tokens[self.idx];
+            let ty = self.parse_type();
+            // structs and enums are returned as QBE aggregates, like they're passed, there's no such type for arrays
+            if matches!(ty, Type::Array(..) | Type::Str) {
+                panic!("{loc} error: functions can't return {ty} yet, pass a pointer to write the result through instead",
+                       loc = ty_token.loc)
+            }
             Some(ty)
         };
 
+        FnSig { ret_ty, args, name_token }
+    }
+
+    fn parse_fn(&mut self) -> Fn<'a> {
+        let FnSig { ret_ty, args, name_token } = self.parse_fn_sig();
+
         let ref lcurly_token = 'lcurly: loop {
             if self.tokens.is_empty() {
                 panic!("{loc} expected `{{` bruv", loc = name_token.loc);
@@ -300,6 +631,16 @@ impl<'a, 'b> Parser<'a, 'b> {
             }
         };
 
+        self.push_scope(ScopeKind::Fn);
+        self.curr_fn = Some(name_token.string);
+
+        // parameters are the first declarations of the function, so they keep their own names
+        let mut args = args;
+        for arg in args.iter_mut() {
+            let sym = self.declare(&arg.name_token, &arg.ty, SymKind::Local);
+            arg.stack = matches!(sym.kind, SymKind::Stack);
+        }
+
         let mut body = Asts::new();
         while !self.parse_line(true, &mut body) {
             self.advance();
@@ -308,24 +649,212 @@ impl<'a, 'b> Parser<'a, 'b> {
             }
         }
 
+        self.pop_scope();
+        self.curr_fn = None;
         self.advance();
 
         Fn { ret_ty, body: body.asts, args, name_token }
     }
 
-    #[inline(always)]
-    fn append(&self, asts: &mut Asts<'a>, loc: Box::<Loc>, kind: AstKind<'a>) {
-        asts.append(self.ctx.to_owned(), loc, kind);
+    fn parse_block(&mut self) -> Vec::<Box::<Ast<'a>>> {
+        let ref lcurly_token = self.tokens[self.idx];
+        self.advance();
+
+        self.push_scope(ScopeKind::Block);
+
+        let mut body = Asts::new();
+        if !self.parse_line(true, &mut body) {
+            panic!("{loc} error: no matching `}}` for this block", loc = lcurly_token.loc)
+        }
+
+        self.pop_scope();
+        self.advance();
+
+        body.asts
     }
 
-    #[inline(always)]
-    fn new_ast(&self, asts: &mut Asts<'a>, loc: Box::<Loc>, kind: AstKind<'a>) -> Ast<'a> {
-        asts.new_ast(self.ctx.to_owned(), loc, kind)
+    /// Parses `return;` or `return value;`, the value is converted to the return type of the enclosing function.
+    fn parse_return(&mut self) -> Option::<Box::<Expr<'a>>> {
+        let return_token = self.tokens[self.idx].to_owned();
+        let Some(sig) = self.curr_fn.map(|name| self.fns[name].to_owned()) else {
+            panic!("{loc} error: `return` outside of a function", loc = return_token.loc)
+        };
+        let name = sig.name_token.string;
+
+        self.advance();
+
+        if self.tokens[self.idx].kind == TokenKind::Semicolon {
+            self.advance();
+            if let Some(ty) = sig.ret_ty {
+                panic!("{loc} error: `{name}` returns {ty}, but `return` has no value", loc = return_token.loc)
+            }
+            return None
+        }
+
+        let Some(ty) = sig.ret_ty else {
+            panic!("{loc} error: `{name}` doesn't return a value, but `return` has one", loc = return_token.loc)
+        };
+
+        let expr_tokens = self.parse_expr_tokens(&return_token.loc, "return value");
+        let ref sym_map = self.visible();
+        let expr = ExprParser::new(expr_tokens, sym_map, &self.fns).parse();
+        let value = expr.coerce(&ty, sym_map, &return_token.loc).unwrap_or_else(|err| {
+            panic!("{loc} error: return value of `{name}`: {err}", loc = return_token.loc)
+        });
+        Some(value)
+    }
+
+    /// Parses `match value { Variant(a, _) => { ... } _ => { ... } }`, every variant of the enum has to be matched.
+    fn parse_match(&mut self) -> Match<'a> {
+        let ref match_token = self.tokens[self.idx];
+        self.advance();
+
+        let mut depth = 0_usize;
+        let mut value_tokens = Vec::new();
+        while !self.eof && (depth > 0 || self.tokens[self.idx].kind != TokenKind::LCurly) {
+            match self.tokens[self.idx].kind {
+                TokenKind::LParen | TokenKind::LBracket => depth += 1,
+                TokenKind::RParen | TokenKind::RBracket => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            value_tokens.push(&self.tokens[self.idx]);
+            self.advance();
+        }
+
+        if self.eof {
+            panic!("{loc} error: expected `{{` after the value to match on", loc = match_token.loc)
+        }
+        if value_tokens.is_empty() {
+            panic!("{loc} error: expected a value to match on", loc = match_token.loc)
+        }
+
+        let ref sym_map = self.visible();
+        let value = ExprParser::new(value_tokens, sym_map, &self.fns).parse();
+        let Type::Enum(def) = value.type_check(sym_map, &match_token.loc) else {
+            panic!("{loc} error: only enums can be matched on, but got: {ty}",
+                   loc = match_token.loc, ty = value.type_check(sym_map, &match_token.loc))
+        };
+
+        self.advance();
+
+        let mut arms = Vec::<MatchArm>::new();
+        let mut wildcard = None::<&Token>;
+        while !self.eof && self.tokens[self.idx].kind != TokenKind::RCurly {
+            let ref pattern_token = self.tokens[self.idx];
+            if let Some(wildcard) = wildcard {
+                panic!("{loc} error: unreachable pattern\n{prev_loc} note: `_` above matches every variant already",
+                       loc = pattern_token.loc, prev_loc = wildcard.loc)
+            }
+
+            let (variant, fields) = match pattern_token.kind {
+                TokenKind::Underscore => {
+                    wildcard = Some(pattern_token);
+                    (None, Vec::new())
+                }
+                TokenKind::Lit => {
+                    let Some((tag, variant)) = def.variant(pattern_token.string) else {
+                        panic!("{loc} error: enum `{name}` has no variant `{vname}`",
+                               loc = pattern_token.loc, name = def.name, vname = pattern_token.string)
+                    };
+                    if arms.iter().any(|arm| arm.variant == Some(tag)) {
+                        panic!("{loc} error: variant `{vname}` is matched twice", loc = pattern_token.loc, vname = variant.name)
+                    }
+                    (Some(tag), variant.fields.to_owned())
+                }
+                _ => panic!("{loc} error: expected name of a variant or `_`, but got: {string}",
+                            loc = pattern_token.loc, string = pattern_token.string)
+            };
+
+            self.advance();
+
+            let mut binding_tokens = Vec::new();
+            if self.tokens[self.idx].kind == TokenKind::LParen {
+                self.advance();
+                while !self.eof && self.tokens[self.idx].kind != TokenKind::RParen {
+                    let binding_token = self.type_check_token_owned(|t| {
+                        matches!(t.kind, TokenKind::Lit | TokenKind::Underscore)
+                    }, |string, loc| {
+                        panic!("{loc} error: expected name of the binding or `_`, but got: {string}")
+                    });
+                    self.advance();
+                    if binding_token.kind == TokenKind::Lit && binding_tokens.iter().any(|t: &Box::<Token>| t.string == binding_token.string) {
+                        panic!("{loc} error: `{name}` is bound more than once in the same pattern",
+                               loc = binding_token.loc, name = binding_token.string)
+                    }
+                    binding_tokens.push(binding_token);
+                    match self.tokens[self.idx].kind {
+                        TokenKind::Comma => self.advance(),
+                        TokenKind::RParen => {}
+                        _ => panic!("{loc} error: expected comma or `)` after the binding, but got: {string}",
+                                    loc = self.tokens[self.idx].loc, string = self.tokens[self.idx].string)
+                    }
+                }
+                self.advance();
+            }
+
+            if binding_tokens.len() != fields.len() {
+                panic!("{loc} error: pattern binds {n} field(s), but variant `{vname}` has {m}",
+                       loc = pattern_token.loc, n = binding_tokens.len(), m = fields.len(), vname = pattern_token.string)
+            }
+
+            self.type_check_token(|t| {
+                matches!(t.kind, TokenKind::FatArrow)
+            }, |string, loc| {
+                panic!("{loc} error: expected `=>` after the pattern, but got: {string}")
+            });
+
+            self.advance();
+
+            self.type_check_token(|t| {
+                matches!(t.kind, TokenKind::LCurly)
+            }, |string, loc| {
+                panic!("{loc} error: expected `{{` after `=>`, but got: {string}")
+            });
+
+            // the bindings are only visible in the body of the arm
+            self.push_scope(ScopeKind::Block);
+            let bindings = binding_tokens.into_iter().zip(fields).map(|(name_token, (ty, _))| {
+                if name_token.kind == TokenKind::Underscore { return None }
+                let sym = self.declare(&name_token, &ty, SymKind::Local);
+                Some(VarDecl {
+                    ty,
+                    global: false,
+                    stack: matches!(sym.kind, SymKind::Stack),
+                    il_name: sym.il_name,
+                    value: None,
+                })
+            }).collect();
+            let body = self.parse_block();
+            self.pop_scope();
+
+            arms.push(MatchArm { variant, bindings, body });
+
+            if self.tokens[self.idx].kind == TokenKind::Comma { self.advance() }
+        }
+
+        if self.eof {
+            panic!("{loc} error: no matching `}}` for this match", loc = match_token.loc)
+        }
+
+        self.advance();
+
+        if wildcard.is_none() {
+            let missing = def.variants.iter().enumerate()
+                .filter(|(tag, _)| arms.iter().all(|arm| arm.variant != Some(*tag)))
+                .map(|(_, variant)| format!("`{name}`", name = variant.name))
+                .collect::<Vec::<_>>();
+            if !missing.is_empty() {
+                panic!("{loc} error: non-exhaustive match on `{name}`, missing: {missing}, add the arms or a `_` one",
+                       loc = match_token.loc, name = def.name, missing = missing.join(", "))
+            }
+        }
+
+        Match { value, def, arms }
     }
 
     #[inline(always)]
-    fn set_ctx(&mut self, ctx: Ctx<'a>) {
-        self.ctx = Box::new(ctx)
+    fn append(&self, asts: &mut Asts<'a>, loc: Box::<Loc>, kind: AstKind<'a>) {
+        asts.append(self.ctx(), loc, kind);
     }
 
     fn parse_line(&mut self, expect_matching: bool, asts: &mut Asts<'a>) -> bool {
@@ -340,35 +869,575 @@ impl<'a, 'b> Parser<'a, 'b> {
                     panic!("{loc} unexpected `}}` bruv", loc = token.loc)
                 }
                 TokenKind::Fn => {
+                    if self.scopes.len() > 1 {
+                        panic!("{loc} error: functions can only be defined at the top level", loc = token.loc)
+                    }
                     let fn_ = Box::new(self.parse_fn());
-                    self.set_ctx(Ctx::empty_fn());
                     self.append(asts, token.loc.to_owned(), AstKind::Fn(fn_));
                 }
-                TokenKind::Lit => {
+                TokenKind::LCurly => {
+                    let block = self.parse_block();
+                    self.append(asts, token.loc.to_owned(), AstKind::Block(block));
+                }
+                TokenKind::Asterisk | TokenKind::LParen => {
+                    let assign = Box::new(self.parse_assign());
+                    self.append(asts, token.loc.to_owned(), AstKind::Assign(assign));
+                }
+                TokenKind::Struct | TokenKind::Enum => {
+                    // collected already, skip over the definition
+                    let ty = self.user_type(self.tokens[self.idx + 1].string);
+                    while !self.eof && self.tokens[self.idx].kind != TokenKind::RCurly { self.advance() }
+                    self.advance();
+                    match ty {
+                        Some(Type::Struct(def)) => self.append(asts, token.loc.to_owned(), AstKind::Struct(def)),
+                        Some(Type::Enum(def)) => self.append(asts, token.loc.to_owned(), AstKind::Enum(def)),
+                        _ => unreachable!()
+                    }
+                }
+                TokenKind::Match => {
+                    let match_ = Box::new(self.parse_match());
+                    self.append(asts, token.loc.to_owned(), AstKind::Match(match_));
+                }
+                TokenKind::Return => {
+                    let value = self.parse_return();
+                    self.append(asts, token.loc.to_owned(), AstKind::Return(value));
+                }
+                // `Shape.Circle(1.0)` on its own isn't a statement, so a name of a type always starts a declaration
+                TokenKind::Lit if self.user_type(token.string).is_some() => self.parse_decl_line(token, asts),
+                TokenKind::Lit => if matches!(self.tokens.get(self.idx + 1), Some(t) if matches!(t.kind, TokenKind::Equal | TokenKind::LBracket | TokenKind::Dot)) {
+                    let assign = Box::new(self.parse_assign());
+                    self.append(asts, token.loc.to_owned(), AstKind::Assign(assign));
+                } else {
                     let fcall = Box::new(self.parse_fn_call());
                     self.append(asts, token.loc.to_owned(), AstKind::FnCall(fcall));
                 }
-                TokenKind::Type => {
-                    let decl = Box::new(self.parse_decl());
-                    let name = decl.name_token.string;
-                    let ast = self.new_ast(asts, token.loc.to_owned(), AstKind::VarDecl(decl));
-                    let ptr = Box::new(ast);
-                    self.ctx.sym_map_mut().insert(name, ptr.to_owned());
-                    asts.append_ast(ptr);
-                    asts.id += 1;
-                }
+                TokenKind::Const | TokenKind::Let | TokenKind::Type => self.parse_decl_line(token, asts),
                 _ => self.idx += 1
             }
         } false
     }
 
+    fn parse_decl_line(&mut self, token: &Token<'a>, asts: &mut Asts<'a>) {
+        // the initializer is compiled in the scope before the declaration
+        let ctx = self.ctx();
+        let constant = token.kind == TokenKind::Const;
+        if constant { self.advance() }
+        let decl = Box::new(self.parse_decl(constant));
+        // constants are folded into their uses, so there's nothing left to compile
+        if !constant {
+            asts.append(ctx, token.loc.to_owned(), AstKind::VarDecl(decl));
+        }
+    }
+
+    /// Whether the `&` at `idx` takes an address, rather than being a binary operator: then there's no operand before it.
+    #[inline]
+    fn is_addr_of(&self, idx: usize) -> bool {
+        !matches! {
+            idx.checked_sub(1).map(|idx| &self.tokens[idx].kind),
+            Some(TokenKind::Lit | TokenKind::Int | TokenKind::Flt | TokenKind::Str | TokenKind::Char |
+                 TokenKind::Bool | TokenKind::Null | TokenKind::Type | TokenKind::RParen | TokenKind::RBracket)
+        }
+    }
+
+    /// Parses `struct Name { T field; ... }`, fields may only have types defined before the struct.
+    fn parse_struct_def(&mut self) -> StructDef {
+        self.advance();
+
+        let name_token = self.type_check_token_owned(|t| {
+            matches!(t.kind, TokenKind::Lit)
+        }, |string, loc| {
+            panic!("{loc} error: expected name of the struct, but got: {string}")
+        });
+
+        self.advance();
+
+        self.type_check_token(|t| {
+            matches!(t.kind, TokenKind::LCurly)
+        }, |string, loc| {
+            panic!("{loc} error: expected `{{` after the name of the struct, but got: {string}")
+        });
+
+        self.advance();
+
+        let mut fields = Vec::<(String, Type)>::new();
+        while !self.eof && self.tokens[self.idx].kind != TokenKind::RCurly {
+            let ref ty_token = self.tokens[self.idx];
+            let ty = self.parse_type();
+            if !ty.is_elem() {
+                panic!("{loc} error: fields of type {ty} aren't supported", loc = ty_token.loc)
+            }
+
+            let field_token = self.type_check_token_owned(|t| {
+                matches!(t.kind, TokenKind::Lit)
+            }, |string, loc| {
+                panic!("{loc} error: expected name of the field, but got: {string}")
+            });
+
+            self.advance();
+
+            if fields.iter().any(|(name, ..)| name == field_token.string) {
+                panic!("{loc} error: duplicate field: {name}", loc = field_token.loc, name = field_token.string)
+            }
+
+            self.type_check_token(|t| {
+                matches!(t.kind, TokenKind::Semicolon)
+            }, |string, loc| {
+                panic!("{loc} error: expected `;` after the field, but got: {string}")
+            });
+
+            self.advance();
+
+            fields.push((field_token.string.to_owned(), ty));
+        }
+
+        if self.eof {
+            panic!("{loc} error: no matching `}}` for this struct", loc = name_token.loc)
+        }
+        if fields.is_empty() {
+            panic!("{loc} error: struct `{name}` must have at least one field", loc = name_token.loc, name = name_token.string)
+        }
+
+        self.advance();
+
+        StructDef::new(name_token.string.to_owned(), fields)
+    }
+
+    /// Parses `enum Name { A, B(T, ...), ... }`, just like fields of structs, payloads may only have types defined before the enum.
+    fn parse_enum_def(&mut self) -> EnumDef {
+        self.advance();
+
+        let name_token = self.type_check_token_owned(|t| {
+            matches!(t.kind, TokenKind::Lit)
+        }, |string, loc| {
+            panic!("{loc} error: expected name of the enum, but got: {string}")
+        });
+
+        self.advance();
+
+        self.type_check_token(|t| {
+            matches!(t.kind, TokenKind::LCurly)
+        }, |string, loc| {
+            panic!("{loc} error: expected `{{` after the name of the enum, but got: {string}")
+        });
+
+        self.advance();
+
+        let mut variants = Vec::<(String, Vec::<Type>)>::new();
+        while !self.eof && self.tokens[self.idx].kind != TokenKind::RCurly {
+            let variant_token = self.type_check_token_owned(|t| {
+                matches!(t.kind, TokenKind::Lit)
+            }, |string, loc| {
+                panic!("{loc} error: expected name of the variant, but got: {string}")
+            });
+
+            self.advance();
+
+            if variants.iter().any(|(name, ..)| name == variant_token.string) {
+                panic!("{loc} error: duplicate variant: {name}", loc = variant_token.loc, name = variant_token.string)
+            }
+
+            let mut tys = Vec::new();
+            if self.tokens[self.idx].kind == TokenKind::LParen {
+                self.advance();
+                while !self.eof && self.tokens[self.idx].kind != TokenKind::RParen {
+                    let ref ty_token = self.tokens[self.idx];
+                    let ty = self.parse_type();
+                    if !ty.is_elem() {
+                        panic!("{loc} error: payloads of type {ty} aren't supported", loc = ty_token.loc)
+                    }
+                    tys.push(ty);
+                    match self.tokens[self.idx].kind {
+                        TokenKind::Comma => self.advance(),
+                        TokenKind::RParen => {}
+                        _ => panic!("{loc} error: expected comma or `)` after the type of the payload, but got: {string}",
+                                    loc = self.tokens[self.idx].loc, string = self.tokens[self.idx].string)
+                    }
+                }
+                if tys.is_empty() {
+                    panic!("{loc} error: variant `{name}` has an empty payload, leave out the parentheses instead",
+                           loc = variant_token.loc, name = variant_token.string)
+                }
+                self.advance();
+            }
+
+            variants.push((variant_token.string.to_owned(), tys));
+
+            match self.tokens[self.idx].kind {
+                TokenKind::Comma => self.advance(),
+                TokenKind::RCurly => {}
+                _ => panic!("{loc} error: expected comma or `}}` after the variant, but got: {string}",
+                            loc = self.tokens[self.idx].loc, string = self.tokens[self.idx].string)
+            }
+        }
+
+        if self.eof {
+            panic!("{loc} error: no matching `}}` for this enum", loc = name_token.loc)
+        }
+        if variants.is_empty() {
+            panic!("{loc} error: enum `{name}` must have at least one variant", loc = name_token.loc, name = name_token.string)
+        }
+
+        self.advance();
+
+        EnumDef::new(name_token.string.to_owned(), variants)
+    }
+
+    /// Pre-pass collecting the struct and enum definitions, so that function signatures can use them.
+    fn collect_types(&mut self) {
+        let mut depth = 0_usize;
+        while !self.eof {
+            let ref token = self.tokens[self.idx];
+            match token.kind {
+                TokenKind::Struct | TokenKind::Enum if depth == 0 => {
+                    let ref name_token = self.tokens[self.idx + 1];
+                    let ty = if token.kind == TokenKind::Struct {
+                        Type::Struct(Rc::new(self.parse_struct_def()))
+                    } else {
+                        Type::Enum(Rc::new(self.parse_enum_def()))
+                    };
+                    if Type::from_name(name_token.string).is_some() || BUILTINS.contains(&name_token.string) {
+                        panic!("{loc} error: `{name}` is a builtin name, it can't be redefined",
+                               loc = name_token.loc, name = name_token.string)
+                    }
+                    if self.scopes[0].sym_map.contains_key(name_token.string) {
+                        panic!("{loc} error: type `{name}` is already defined",
+                               loc = name_token.loc, name = name_token.string)
+                    }
+                    let sym = Sym { ty, kind: SymKind::Type, il_name: name_token.string.to_owned() };
+                    self.scopes[0].sym_map.insert(name_token.string, sym);
+                    continue
+                }
+                TokenKind::Struct | TokenKind::Enum => panic!("{loc} error: types can only be defined at the top level", loc = token.loc),
+                TokenKind::LCurly => depth += 1,
+                TokenKind::RCurly => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            self.advance();
+        }
+
+        self.idx = 0;
+        self.eof = false;
+    }
+
+    /// Pre-pass collecting the signatures of all functions, so they can be called before they're defined,
+    /// and the names whose address is taken, so they can be put into stack slots right where they're declared.
     #[inline(always)]
-    pub fn parse(&mut self) -> Asts {
+    fn collect_fns(&mut self) {
+        let mut depth = 0_usize;
+        let mut curr_fn = None;
+        while !self.eof {
+            match self.tokens[self.idx].kind {
+                TokenKind::Ampersand if self.is_addr_of(self.idx) => {
+                    // parentheses don't make it into the tree, so `&(x)` takes the address of `x` too
+                    let operand = self.tokens[self.idx + 1..].iter().find(|t| t.kind != TokenKind::LParen);
+                    if let Some(t) = operand.filter(|t| t.kind == TokenKind::Lit) {
+                        self.addressed.insert((curr_fn, t.string));
+                    }
+                }
+                TokenKind::Fn if depth == 0 => {
+                    let sig = self.parse_fn_sig();
+                    curr_fn = Some(sig.name_token.string);
+                    let ref name_token = sig.name_token;
+                    if BUILTINS.contains(&name_token.string) {
+                        panic!("{loc} error: `{name}` is a builtin function, it can't be redefined",
+                               loc = name_token.loc, name = name_token.string)
+                    }
+                    if self.user_type(name_token.string).is_some() {
+                        panic!("{loc} error: `{name}` is already defined as a type",
+                               loc = name_token.loc, name = name_token.string)
+                    }
+                    if let Some(prev) = self.fns.get(name_token.string) {
+                        panic!("{loc} error: function `{name}` is already defined\n{prev_loc} note: first defined here",
+                               loc = name_token.loc, name = name_token.string, prev_loc = prev.name_token.loc)
+                    }
+                    self.fns.insert(name_token.string, sig);
+                    continue
+                }
+                TokenKind::LCurly => depth += 1,
+                TokenKind::RCurly => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 { curr_fn = None }
+                }
+                _ => {}
+            }
+            self.advance();
+        }
+
+        self.idx = 0;
+        self.eof = false;
+    }
+
+    pub fn parse(&mut self) -> Asts<'a> {
+        self.collect_types();
+        self.collect_fns();
+
         let mut asts = Asts::new();
         while self.idx < self.tokens.len() && !self.eof {
             if self.eof { break }
             self.parse_line(false, &mut asts);
             self.advance();
-        } asts
+        }
+
+        if let Some(main) = self.fns.get(ENTRY) {
+            if let Some(stmt) = asts.asts.iter().find(|ast| ast.is_stmt()) {
+                panic!("{loc} error: top-level statements aren't allowed when `{ENTRY}` is defined\n{main_loc} note: `{ENTRY}` defined here",
+                       loc = stmt.loc, main_loc = main.name_token.loc)
+            }
+        }
+
+        asts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(src: &str) -> Asts<'static> {
+        let content: &'static String = Box::leak(Box::new(src.to_owned()));
+        let mut lexer = Lexer::new("test.w", Ok(content)).unwrap();
+        lexer.lex();
+        Parser::new(&lexer.tokens).parse()
+    }
+
+    /// Names the declarations are compiled to, in the order they appear in, functions and blocks included.
+    fn il_names(asts: &[Box::<Ast>]) -> Vec::<String> {
+        asts.iter().flat_map(|ast| match &ast.kind {
+            AstKind::VarDecl(vd) => vec![vd.il_name.to_owned()],
+            AstKind::Fn(fn_) => fn_.args.iter().map(|arg| arg.name_token.string.to_owned()).chain(il_names(&fn_.body)).collect(),
+            AstKind::Block(body) => il_names(body),
+            _ => vec![]
+        }).collect()
+    }
+
+    #[test]
+    fn shadowing_declarations_get_unique_names() {
+        let asts = parse("fn main() { i64 x = 1; { i64 x = 2; { i64 x = 3; } } i64 y = x; }");
+        assert_eq!(il_names(&asts.asts), ["x", "x.1", "x.2", "y"]);
+    }
+
+    #[test]
+    fn parameters_can_be_shadowed() {
+        let asts = parse("fn f(i64 a) { i64 a = 2; }");
+        assert_eq!(il_names(&asts.asts), ["a", "a.1"]);
+    }
+
+    #[test]
+    fn every_function_counts_shadows_from_scratch() {
+        let asts = parse("fn f() { i64 x = 1; i64 x = 2; }\nfn g() { i64 x = 3; }");
+        assert_eq!(il_names(&asts.asts), ["x", "x.1", "x"]);
+    }
+
+    #[test]
+    fn blocks_see_the_enclosing_scopes() {
+        let asts = parse("i64 g = 1;\nfn main() { i64 x = g; { i64 y = x + g; } }");
+        assert_eq!(il_names(&asts.asts), ["g.g", "x", "y"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "undefined symbol: y")]
+    fn declarations_end_with_their_block() {
+        parse("fn main() { { i64 y = 1; } i64 z = y; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "undefined symbol: x")]
+    fn locals_of_other_functions_are_not_visible() {
+        parse("fn f() { i64 x = 1; }\nfn g() { i64 y = x; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "`g` is already defined at the top level")]
+    fn globals_can_not_be_redefined() {
+        parse("i64 g = 1;\ni64 g = 2;");
+    }
+
+    #[test]
+    fn constant_indices_within_bounds() {
+        parse("fn main() { i64[3] xs = [1, 2, 3]; i64 y = xs[0] + xs[2]; xs[1] = 5; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds: the length is 3 but the index is 3")]
+    fn constant_index_past_the_end() {
+        parse("fn main() { i64[3] xs = [1, 2, 3]; i64 y = xs[3]; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds: the length is 3 but the index is -1")]
+    fn negative_constant_index() {
+        parse("fn main() { i64[3] xs = [1, 2, 3]; xs[1 - 2] = 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "error: division by zero")]
+    fn constant_division_by_zero() {
+        parse("fn main() { i64 c = 10 / 0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "error: division by zero")]
+    fn constant_remainder_by_zero() {
+        parse("fn main() { i64 d = 7 % (2 - 2); }");
+    }
+
+    #[test]
+    #[should_panic(expected = "error: division by zero")]
+    fn float_division_by_zero() {
+        parse("fn main() { f64 x = 1.5; f64 y = x / 0.0; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "error: division by zero")]
+    fn constant_index_divided_by_zero() {
+        parse("fn main() { i64[3] xs = [1, 2, 3]; i64 y = xs[1 / 0]; }");
+    }
+
+    #[test]
+    fn division_by_a_variable_is_left_for_runtime() {
+        parse("fn main() { i64 z = 0; i64 c = 10 / z + 7 % z; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "expected 3 elements, but the array literal has 2")]
+    fn array_literal_of_the_wrong_length() {
+        parse("fn main() { i64[3] xs = [1, 2]; }");
+    }
+
+    const POINT: &str = "struct Point { f64 x; f64 y; }\n";
+
+    #[test]
+    fn structs_are_passed_and_returned_by_value() {
+        parse(&format!("{POINT}fn mk(f64 x) -> Point {{ return Point {{ x: x, y: 2 }}; }}\n\
+                        fn len(Point p) -> f64 {{ return p.x + p.y; }}\n\
+                        fn main() {{ Point p = mk(1); p.y = mk(3).x; f64 l = len(p); }}"));
+    }
+
+    #[test]
+    #[should_panic(expected = "missing field `y` in the literal of `Point`")]
+    fn struct_literal_missing_a_field() {
+        parse(&format!("{POINT}fn main() {{ Point p = Point {{ x: 1.0 }}; }}"));
+    }
+
+    #[test]
+    #[should_panic(expected = "field `x` is initialized twice")]
+    fn struct_literal_initializing_a_field_twice() {
+        parse(&format!("{POINT}fn main() {{ Point p = Point {{ x: 1.0, y: 2.0, x: 3.0 }}; }}"));
+    }
+
+    #[test]
+    #[should_panic(expected = "struct `Point` has no field `z`")]
+    fn access_to_an_unknown_field() {
+        parse(&format!("{POINT}fn main() {{ Point p = Point {{ x: 1.0, y: 2.0 }}; f64 z = p.z; }}"));
+    }
+
+    #[test]
+    #[should_panic(expected = "expected a value of type Point, but got: f64")]
+    fn struct_argument_of_the_wrong_type() {
+        parse(&format!("{POINT}fn f(Point p) {{}}\nfn main() {{ f(1.0) }}"));
+    }
+
+    #[test]
+    #[should_panic(expected = "functions can't return i64[2] yet")]
+    fn arrays_can_not_be_returned() {
+        parse("fn f() -> i64[2] {}");
+    }
+
+    const SHAPE: &str = "enum Shape { Circle(f64), Rect(f64, f64), Empty }\n";
+
+    #[test]
+    fn matches_bind_the_payload() {
+        let asts = parse(&format!("{SHAPE}fn area(Shape s) -> f64 {{ match s {{ Circle(r) => {{ return r * r; }} \
+                                   Rect(w, _) => {{ return w; }} Empty => {{ return 0.0; }} }} }}\n\
+                                   fn main() {{ f64 a = area(Shape.Rect(1, 2)); }}"));
+        let Some(AstKind::Fn(area)) = asts.asts.iter().map(|ast| &ast.kind).find(|kind| matches!(kind, AstKind::Fn(..))) else {
+            panic!("expected `area` to be parsed")
+        };
+        let Some(AstKind::Match(match_)) = area.body.first().map(|ast| &ast.kind) else {
+            panic!("expected the body of `area` to be a match")
+        };
+        let arms = match_.arms.iter().map(|arm| (arm.variant, arm.bindings.iter().map(Option::is_some).collect::<Vec::<_>>())).collect::<Vec::<_>>();
+        assert_eq!(arms, [(Some(0), vec![true]), (Some(1), vec![true, false]), (Some(2), vec![])]);
+    }
+
+    #[test]
+    fn wildcard_arm_makes_a_match_exhaustive() {
+        parse(&format!("{SHAPE}fn main() {{ Shape s = Shape.Empty; match s {{ Circle(r) => {{}} _ => {{}} }} }}"));
+    }
+
+    #[test]
+    #[should_panic(expected = "non-exhaustive match on `Shape`, missing: `Rect`, `Empty`")]
+    fn non_exhaustive_match() {
+        parse(&format!("{SHAPE}fn main() {{ Shape s = Shape.Empty; match s {{ Circle(r) => {{}} }} }}"));
+    }
+
+    #[test]
+    #[should_panic(expected = "variant `Empty` is matched twice")]
+    fn variant_matched_twice() {
+        parse(&format!("{SHAPE}fn main() {{ Shape s = Shape.Empty; match s {{ Empty => {{}} Empty => {{}} _ => {{}} }} }}"));
+    }
+
+    #[test]
+    #[should_panic(expected = "unreachable pattern")]
+    fn arm_after_a_wildcard() {
+        parse(&format!("{SHAPE}fn main() {{ Shape s = Shape.Empty; match s {{ _ => {{}} Empty => {{}} }} }}"));
+    }
+
+    #[test]
+    #[should_panic(expected = "pattern binds 1 field(s), but variant `Rect` has 2")]
+    fn pattern_with_the_wrong_number_of_bindings() {
+        parse(&format!("{SHAPE}fn main() {{ Shape s = Shape.Empty; match s {{ Rect(w) => {{}} _ => {{}} }} }}"));
+    }
+
+    #[test]
+    #[should_panic(expected = "variant `Shape.Rect` has 2 field(s), but 1 were supplied")]
+    fn variant_with_a_missing_payload() {
+        parse(&format!("{SHAPE}fn main() {{ Shape s = Shape.Rect(1.0); }}"));
+    }
+
+    #[test]
+    #[should_panic(expected = "only enums can be matched on, but got: i64")]
+    fn match_on_an_integer() {
+        parse("fn main() { i64 x = 1; match x { _ => {} } }");
+    }
+
+    #[test]
+    #[should_panic(expected = "can't infer the type of `null`, annotate it: `let p: T* = null;`")]
+    fn let_initialized_with_null() {
+        parse("fn main() { let p = null; f64 x = *p; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "can't infer the type of the pointer, annotate it: `let p: T* = ...;`")]
+    fn let_initialized_with_alloc() {
+        parse("fn main() { let p = alloc(8); }");
+    }
+
+    #[test]
+    fn let_with_an_annotated_pointer() {
+        parse("fn main() { let p: f64* = null; let q: i64* = alloc(8); i64 x = 1; let r = &x; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "`syscall` needs the number of the syscall")]
+    fn syscall_without_a_number() {
+        parse("fn main() { i64 r = syscall(); }");
+    }
+
+    #[test]
+    #[should_panic(expected = "`syscall` takes the number of the syscall and up to 6 arguments, but 8 were given")]
+    fn syscall_with_too_many_arguments() {
+        parse("fn main() { i64 r = syscall(1, 2, 3, 4, 5, 6, 7, 8); }");
+    }
+
+    #[test]
+    fn number_readers_take_a_status() {
+        parse("fn main() { i64 status = 0; i64 x = read_i64(&status); f64 y = read_f64(null); }");
+    }
+
+    #[test]
+    #[should_panic(expected = "argument 1 of `read_i64` must be an i64*, but got: u8*")]
+    fn number_readers_store_the_status_as_an_i64() {
+        parse("fn main() { u8 status = 0; i64 x = read_i64(&status); }");
     }
 }